@@ -0,0 +1,105 @@
+use std::collections::BTreeSet;
+
+use crate::{max_over_reveals, Reveal};
+
+
+/// Most likely bag for a game, along with its share of the total likelihood over all
+/// the candidate bags that were considered.
+pub struct BagEstimate {
+    pub bag: Reveal,
+    pub log_likelihood: f64,
+    pub confidence: f64,
+}
+
+/// `ln(n!)` for every `n` up to `max_n`.
+fn log_factorials(max_n: u32) -> Vec<f64> {
+    let mut table = vec![0.0; max_n as usize + 1];
+    for n in 1..=max_n as usize {
+        table[n] = table[n - 1] + (n as f64).ln();
+    }
+    table
+}
+
+fn log_binomial(log_fact: &[f64], n: u32, k: u32) -> f64 {
+    if k > n {
+        return f64::NEG_INFINITY;
+    }
+    log_fact[n as usize] - log_fact[k as usize] - log_fact[(n - k) as usize]
+}
+
+/// Log-probability of seeing `reveal` when grabbing that many cubes at once from the bag
+/// holding `counts[i]` cubes of `colors[i]`, i.e. a multivariate hypergeometric draw.
+/// Cubes go back in the bag between reveals.
+fn log_likelihood_reveal(log_fact: &[f64], colors: &[&String], counts: &[u32], reveal: &Reveal) -> f64 {
+    let bag_total: u32 = counts.iter().sum();
+    let drawn_total: u32 = reveal.values().sum();
+    let matching: f64 = colors.iter().zip(counts.iter()).map(|(color, n)| log_binomial(log_fact, *n, *reveal.get(*color).unwrap_or(&0))).sum();
+    matching - log_binomial(log_fact, bag_total, drawn_total)
+}
+
+/// Moves `counts` to the next bag holding, for each color, between the observed minimum
+/// and `slack` more cubes, like an odometer. Returns false once every bag has been seen.
+fn next_candidate(counts: &mut [u32], lows: &[u32], slack: u32) -> bool {
+    for (count, low) in counts.iter_mut().zip(lows.iter()) {
+        if *count < low + slack {
+            *count += 1;
+            return true;
+        }
+        *count = *low;
+    }
+    false
+}
+
+/// Maximum-likelihood bag for a game, searched over every bag holding between the
+/// observed minimum and `slack` more cubes of each color. The confidence is the posterior
+/// probability of that bag under a uniform prior on the candidates, so it shrinks as
+/// `slack` grows and more bags explain the reveals. Candidates are scored one at a time,
+/// keeping only the best one and a running log-sum-exp of the likelihoods.
+pub fn estimate_bag(reveals: &[Reveal], colors: &BTreeSet<String>, slack: u32) -> BagEstimate {
+    let minimum = max_over_reveals(reveals);
+    let colors: Vec<&String> = colors.iter().collect();
+    let lows: Vec<u32> = colors.iter().map(|c| *minimum.get(*c).unwrap_or(&0)).collect();
+    let max_total = lows.iter().map(|low| low + slack).sum();
+    let log_fact = log_factorials(max_total);
+
+    let mut counts = lows.clone();
+    let mut best_counts = counts.clone();
+    let mut best_log_likelihood = f64::NEG_INFINITY;
+    // Sum of the likelihoods relative to the best one so far, to keep the exponentials in range
+    let mut total = 0.0;
+    loop {
+        let log_likelihood: f64 = reveals.iter().map(|r| log_likelihood_reveal(&log_fact, &colors, &counts, r)).sum();
+        if log_likelihood > best_log_likelihood {
+            total = total * (best_log_likelihood - log_likelihood).exp() + 1.0;
+            best_log_likelihood = log_likelihood;
+            best_counts.copy_from_slice(&counts);
+        } else {
+            total += (log_likelihood - best_log_likelihood).exp();
+        }
+        if !next_candidate(&mut counts, &lows, slack) {
+            break;
+        }
+    }
+
+    BagEstimate {
+        bag: colors.iter().map(|c| c.to_string()).zip(best_counts).collect(),
+        log_likelihood: best_log_likelihood,
+        confidence: 1.0 / total,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_reveal;
+
+    #[test]
+    fn test_estimate_bag_single_reveal() {
+        let reveals = vec![parse_reveal("3 blue, 4 red")];
+        let colors = BTreeSet::from(["blue".to_string(), "red".to_string()]);
+        let estimate = estimate_bag(&reveals, &colors, 5);
+        assert_eq!(estimate.bag, parse_reveal("3 blue, 4 red"));
+        assert_eq!(estimate.log_likelihood, 0.0);
+        assert!(estimate.confidence > 0.0 && estimate.confidence < 1.0);
+    }
+}
@@ -1,15 +1,19 @@
-use std::cmp::{max, min};
+use std::cmp::max;
+use std::collections::{BTreeMap, BTreeSet};
 use std::{env, fs::File, path::Path};
 use std::io::{self, BufRead};
 use regex::Regex;
 use lazy_static::lazy_static;
 
+mod inference;
+mod simulator;
+
 
 lazy_static! {
     static ref GAME_RE: Regex = Regex::new(r"^Game (\d+): (.*)$").unwrap();
 }
 
-type Reveal = (u32, u32, u32);
+type Reveal = BTreeMap<String, u32>;
 type Game = (u32, Vec<Reveal>);
 
 fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
@@ -19,19 +23,14 @@ where P: AsRef<Path>, {
 }
 
 fn parse_reveal(reveal: &str) -> Reveal {
-    let (mut red, mut green, mut blue): (u32, u32, u32) = (0, 0, 0);
+    let mut parsed = Reveal::new();
     for cubes in reveal.split(", ") {
-        let mut cubes = cubes.split(" ");
+        let mut cubes = cubes.split(' ');
         let number: u32 = cubes.next().unwrap().parse().unwrap();
         let color = cubes.next().unwrap();
-        match color {
-            "red" => red = number,
-            "green" => green = number,
-            "blue" => blue = number,
-            _ => (),
-        }
+        parsed.insert(color.to_string(), number);
     }
-    (red, green, blue)
+    parsed
 }
 
 fn parse_game_line(line: &str) -> Game {
@@ -43,16 +42,124 @@ fn parse_game_line(line: &str) -> Game {
     (game_id, parsed_seq)
 }
 
-fn max_over_reveals(reveals: Vec<Reveal>) -> Reveal {
-    reveals.iter().fold((0, 0, 0), |(r1, g1, b1), (r2, g2, b2)| (max(r1, *r2), max(g1, *g2), max(b1, *b2)))
+fn max_over_reveals(reveals: &[Reveal]) -> Reveal {
+    reveals.iter().fold(Reveal::new(), |mut maxed, reveal| {
+        for (color, number) in reveal.iter() {
+            let current = maxed.entry(color.clone()).or_insert(0);
+            *current = max(*current, *number);
+        }
+        maxed
+    })
+}
+
+fn all_colors(games: &[Game]) -> BTreeSet<String> {
+    games.iter().flat_map(|(_, reveals)| reveals.iter().flat_map(|r| r.keys().cloned())).collect()
+}
+
+/// Whether the `maxed` reveal fits in `bag`. A color absent from the bag is an error
+/// rather than a silent zero, since it usually means the bag was mistyped.
+fn is_feasible(maxed: &Reveal, bag: &Reveal) -> Result<bool, String> {
+    let mut feasible = true;
+    for (color, number) in maxed.iter() {
+        match bag.get(color) {
+            Some(limit) => feasible &= number <= limit,
+            None => return Err(format!("Unknown color in reveal: {color}")),
+        }
+    }
+    Ok(feasible)
+}
+
+/// Product of the minimum cube count over every color seen in the input, so a game
+/// missing one of them has a power of 0.
+fn power(maxed: &Reveal, colors: &BTreeSet<String>) -> u32 {
+    colors.iter().map(|color| *maxed.get(color).unwrap_or(&0)).product()
+}
+
+/// Parses a bag given as `red=12,green=13,blue=14`.
+fn parse_bag(spec: &str) -> Result<Reveal, String> {
+    let mut bag = Reveal::new();
+    for entry in spec.split(',').map(|e| e.trim()).filter(|e| !e.is_empty()) {
+        let (color, number) = entry.split_once('=').ok_or(format!("Invalid bag entry: {entry}"))?;
+        let number: u32 = number.parse().map_err(|_| format!("Invalid cube count in bag entry: {entry}"))?;
+        bag.insert(color.to_string(), number);
+    }
+    Ok(bag)
+}
+
+/// IDs of the games that could have been played with `bag`, reporting games that
+/// reveal a color the bag does not know about.
+fn feasible_games(maxed_games: &[(u32, Reveal)], bag: &Reveal) -> Vec<u32> {
+    let mut feasible = Vec::new();
+    for (game_id, maxed) in maxed_games.iter() {
+        match is_feasible(maxed, bag) {
+            Ok(true) => feasible.push(*game_id),
+            Ok(false) => (),
+            Err(e) => eprintln!("Game {game_id}: {e}"),
+        }
+    }
+    feasible
+}
+
+fn format_bag(bag: &Reveal) -> String {
+    bag.iter().map(|(color, number)| format!("{color}={number}")).collect::<Vec<_>>().join(",")
+}
+
+fn format_ids(ids: &[u32]) -> String {
+    ids.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(", ")
 }
 
 fn main() {
     let args: Vec<String> = env::args().collect();
+    if args[1] == "--simulate" {
+        let bag = parse_bag(&args[2]).unwrap();
+        let nb_games: u32 = args[3].parse().unwrap();
+        let seed: u64 = args[4].parse().unwrap();
+        let simulated = simulator::simulate(&bag, nb_games, seed);
+        eprintln!("Bag: {}", format_bag(&simulated.bag));
+        simulated.lines.iter().for_each(|line| println!("{line}"));
+        return;
+    }
     let filename = &args[1];
-    let maxed_games = read_lines(filename).unwrap().map(|l| parse_game_line(&l.unwrap())).map(|(game_id, reveals)| (game_id, max_over_reveals(reveals)));
-    // let game_id_sum: u32 = maxed_games.filter(|(_, (r, g, b))| *r <= 12 && *g <= 13 && *b <= 14).map(|(game_id, _)| game_id).sum();
-    // println!("Total sum is: {game_id_sum}")
-    let power_sum: u32 = maxed_games.map(|(_, (r, g, b))| r * g * b).sum();
-    println!("Power sum is: {power_sum}")
+    let games: Vec<Game> = read_lines(filename).unwrap().map(|l| parse_game_line(&l.unwrap())).collect();
+    let maxed_games: Vec<(u32, Reveal)> = games.iter().map(|(game_id, reveals)| (*game_id, max_over_reveals(reveals))).collect();
+
+    match args.get(2).map(|s| s.as_str()) {
+        Some("--bag") => {
+            let bag = parse_bag(&args[3]).unwrap();
+            let feasible = feasible_games(&maxed_games, &bag);
+            let game_id_sum: u32 = feasible.iter().sum();
+            println!("Feasible games: {}", format_ids(&feasible));
+            println!("Total sum is: {game_id_sum}");
+        }
+        Some("--bags") => {
+            for (i, line) in read_lines(&args[3]).unwrap().enumerate() {
+                let line = line.unwrap();
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match parse_bag(&line) {
+                    Ok(bag) => {
+                        let feasible = feasible_games(&maxed_games, &bag);
+                        let game_id_sum: u32 = feasible.iter().sum();
+                        println!("{}: sum {game_id_sum} [{}]", line.trim(), format_ids(&feasible));
+                    }
+                    Err(e) => eprintln!("Line {}: {e}", i + 1),
+                }
+            }
+        }
+        Some("--infer") => {
+            let slack: u32 = args.get(3).map(|s| s.parse().unwrap()).unwrap_or(10);
+            let colors = all_colors(&games);
+            for (game_id, reveals) in games.iter() {
+                let estimate = inference::estimate_bag(reveals, &colors, slack);
+                println!("Game {game_id}: {} (log-likelihood {:.3}, confidence {:.3})", format_bag(&estimate.bag), estimate.log_likelihood, estimate.confidence);
+            }
+        }
+        Some(option) => panic!("Unknown option: {option}"),
+        None => {
+            let colors = all_colors(&games);
+            let power_sum: u32 = maxed_games.iter().map(|(_, maxed)| power(maxed, &colors)).sum();
+            println!("Power sum is: {power_sum}")
+        }
+    }
 }
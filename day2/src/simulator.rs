@@ -0,0 +1,102 @@
+use crate::Reveal;
+
+
+/// Game lines drawn from a known bag, kept alongside that bag so solver answers can be
+/// checked against the ground truth.
+pub struct SimulatedGames {
+    pub bag: Reveal,
+    pub lines: Vec<String>,
+}
+
+/// SplitMix64, enough randomness for test inputs without pulling in a dependency.
+struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `low..=high`.
+    fn next_in(&mut self, low: u32, high: u32) -> u32 {
+        low + (self.next() % (high - low + 1) as u64) as u32
+    }
+}
+
+/// Grabs between one and all of the cubes at once, without replacement.
+fn draw_reveal(rng: &mut SplitMix64, bag: &Reveal) -> Reveal {
+    let mut remaining: Vec<(String, u32)> = bag.iter().map(|(c, n)| (c.clone(), *n)).collect();
+    let mut remaining_total: u32 = bag.values().sum();
+    let nb_drawn = rng.next_in(1, remaining_total);
+    let mut reveal = Reveal::new();
+    for _ in 0..nb_drawn {
+        let mut pick = rng.next_in(0, remaining_total - 1);
+        for (color, count) in remaining.iter_mut() {
+            if pick < *count {
+                *count -= 1;
+                *reveal.entry(color.clone()).or_insert(0) += 1;
+                break;
+            }
+            pick -= *count;
+        }
+        remaining_total -= 1;
+    }
+    reveal
+}
+
+/// Formats a reveal the way the puzzle input does, colors in random order.
+fn format_reveal(rng: &mut SplitMix64, reveal: &Reveal) -> String {
+    let mut cubes: Vec<String> = reveal.iter().map(|(color, number)| format!("{number} {color}")).collect();
+    for i in (1..cubes.len()).rev() {
+        cubes.swap(i, rng.next_in(0, i as u32) as usize);
+    }
+    cubes.join(", ")
+}
+
+pub fn simulate(bag: &Reveal, nb_games: u32, seed: u64) -> SimulatedGames {
+    assert!(bag.values().sum::<u32>() > 0, "Cannot draw from an empty bag");
+    let mut rng = SplitMix64 { state: seed };
+    let lines = (1..=nb_games).map(|game_id| {
+        let nb_reveals = rng.next_in(1, 6);
+        let reveals: Vec<String> = (0..nb_reveals).map(|_| {
+            let reveal = draw_reveal(&mut rng, bag);
+            format_reveal(&mut rng, &reveal)
+        }).collect();
+        format!("Game {game_id}: {}", reveals.join("; "))
+    }).collect();
+    SimulatedGames { bag: bag.clone(), lines }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{is_feasible, max_over_reveals, parse_bag, parse_game_line};
+
+    #[test]
+    fn test_simulated_games_fit_their_bag() {
+        let simulated = simulate(&parse_bag("red=12,green=13,blue=14").unwrap(), 200, 42);
+        let smaller_bag = parse_bag("red=6,green=7,blue=8").unwrap();
+        for line in simulated.lines.iter() {
+            let (_, reveals) = parse_game_line(line);
+            let maxed = max_over_reveals(&reveals);
+            for (color, number) in maxed.iter() {
+                assert!(number <= simulated.bag.get(color).unwrap());
+            }
+            assert_eq!(is_feasible(&maxed, &simulated.bag), Ok(true));
+
+            let fits_smaller = maxed.iter().all(|(color, number)| number <= smaller_bag.get(color).unwrap());
+            assert_eq!(is_feasible(&maxed, &smaller_bag), Ok(fits_smaller));
+        }
+    }
+
+    #[test]
+    fn test_simulate_is_deterministic() {
+        let bag = parse_bag("red=3,blue=2").unwrap();
+        assert_eq!(simulate(&bag, 10, 7).lines, simulate(&bag, 10, 7).lines);
+    }
+}
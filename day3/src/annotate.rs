@@ -0,0 +1,109 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{build_gear_map, GearRule, ParsedNumber, ParsedSymbol, Schematic};
+
+
+const RESET: &str = "\x1b[0m";
+const PART: &str = "\x1b[32m";
+const GEAR: &str = "\x1b[1;35m";
+const SYMBOL: &str = "\x1b[1m";
+const IGNORED: &str = "\x1b[2m";
+
+/// Numbers counted as parts, gear symbols, and the numbers attached to those gears,
+/// following the part 1 and part 2 rules.
+struct Classification {
+    parts: HashSet<ParsedNumber>,
+    gears: HashSet<ParsedSymbol>,
+    gear_numbers: HashSet<ParsedNumber>,
+}
+
+fn classify(schematic: &Schematic) -> Classification {
+    let gear_map = build_gear_map(schematic);
+    let (part_rule, gear_rule) = (GearRule::part1(), GearRule::part2());
+    let mut classification = Classification { parts: HashSet::new(), gears: HashSet::new(), gear_numbers: HashSet::new() };
+    for (symbol, numbers) in gear_map.iter() {
+        if part_rule.matches(symbol, numbers.len()) {
+            classification.parts.extend(numbers.iter());
+        }
+        if gear_rule.matches(symbol, numbers.len()) {
+            classification.gears.insert(*symbol);
+            classification.gear_numbers.extend(numbers.iter());
+        }
+    }
+    classification
+}
+
+/// The schematic with ANSI colors: parts in green, gears and their numbers in magenta,
+/// other symbols in bold and numbers that were not counted dimmed.
+pub fn render(schematic: &Schematic) -> String {
+    let classification = classify(schematic);
+    let mut styles: HashMap<(i32, i32), &str> = HashMap::new();
+    for number in schematic.numbers.iter() {
+        let style = if classification.gear_numbers.contains(number) {
+            GEAR
+        } else if classification.parts.contains(number) {
+            PART
+        } else {
+            IGNORED
+        };
+        for x in number.x_start..=number.x_end {
+            styles.insert((x, number.y), style);
+        }
+    }
+    for symbol in schematic.symbols.iter() {
+        styles.insert((symbol.x, symbol.y), if classification.gears.contains(symbol) { GEAR } else { SYMBOL });
+    }
+
+    let mut rendered = String::new();
+    for (y, line) in schematic.lines.iter().enumerate() {
+        let mut current: Option<&str> = None;
+        for (x, grapheme) in line.graphemes(true).enumerate() {
+            let style = styles.get(&(x as i32, y as i32)).copied();
+            if style != current {
+                if current.is_some() {
+                    rendered.push_str(RESET);
+                }
+                rendered.push_str(style.unwrap_or(""));
+                current = style;
+            }
+            rendered.push_str(grapheme);
+        }
+        if current.is_some() {
+            rendered.push_str(RESET);
+        }
+        rendered.push('\n');
+    }
+    rendered
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Writes one row per number with its coordinates, whether it was counted as a part
+/// and the symbols it touches.
+pub fn write_csv(schematic: &Schematic, path: &str) -> io::Result<()> {
+    let classification = classify(schematic);
+    let mut csv = String::from("number,x_start,x_end,y,part,gear,symbols\n");
+    for number in schematic.numbers.iter() {
+        let symbols: Vec<String> = schematic.adjacent_symbols(number).map(|s| format!("{}@{}:{}", s.symbol, s.x, s.y)).collect();
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{}\n",
+            number.number,
+            number.x_start,
+            number.x_end,
+            number.y,
+            classification.parts.contains(number),
+            classification.gear_numbers.contains(number),
+            csv_field(&symbols.join(";")),
+        ));
+    }
+    fs::write(path, csv)
+}
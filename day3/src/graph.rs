@@ -0,0 +1,99 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use petgraph::algo::kosaraju_scc;
+use petgraph::graph::{NodeIndex, UnGraph};
+use petgraph::visit::EdgeRef;
+
+use crate::{build_gear_map, ParsedNumber, ParsedSymbol, Schematic};
+
+
+#[derive(Clone, Copy)]
+pub enum PartNode {
+    Number(ParsedNumber),
+    Symbol(ParsedSymbol),
+}
+
+impl fmt::Display for PartNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PartNode::Number(n) => write!(f, "{} @ ({}, {})", n.number, n.x_start, n.y),
+            PartNode::Symbol(s) => write!(f, "{} @ ({}, {})", s.symbol, s.x, s.y),
+        }
+    }
+}
+
+/// Bipartite graph linking every number to the symbols it is adjacent to.
+pub struct PartGraph {
+    graph: UnGraph<PartNode, ()>,
+}
+
+impl PartGraph {
+    pub fn from_schematic(schematic: &Schematic) -> Self {
+        let mut graph = UnGraph::new_undirected();
+        let number_nodes: HashMap<ParsedNumber, NodeIndex> = schematic.numbers.iter().map(|n| (*n, graph.add_node(PartNode::Number(*n)))).collect();
+        for symbol in schematic.symbols.iter() {
+            graph.add_node(PartNode::Symbol(*symbol));
+        }
+        let symbol_nodes: HashMap<ParsedSymbol, NodeIndex> = graph.node_indices().filter_map(|i| match graph[i] {
+            PartNode::Symbol(s) => Some((s, i)),
+            PartNode::Number(_) => None,
+        }).collect();
+        for (symbol, numbers) in build_gear_map(schematic).iter() {
+            for number in numbers.iter() {
+                graph.add_edge(number_nodes[number], symbol_nodes[symbol], ());
+            }
+        }
+        PartGraph { graph }
+    }
+
+    /// Connected components, each a list of the parts it contains.
+    pub fn components(&self) -> Vec<Vec<PartNode>> {
+        kosaraju_scc(&self.graph).into_iter().map(|c| c.into_iter().map(|i| self.graph[i]).collect()).collect()
+    }
+
+    fn numbers_by_degree<F>(&self, keep: F) -> Vec<(ParsedNumber, usize)>
+    where F: Fn(usize) -> bool, {
+        self.graph.node_indices().filter_map(|i| match self.graph[i] {
+            PartNode::Number(n) => Some((n, self.graph.neighbors(i).count())),
+            PartNode::Symbol(_) => None,
+        }).filter(|(_, degree)| keep(*degree)).collect()
+    }
+
+    /// Numbers adjacent to more than one symbol, with the number of symbols they touch.
+    pub fn shared_numbers(&self) -> Vec<(ParsedNumber, usize)> {
+        self.numbers_by_degree(|degree| degree > 1)
+    }
+
+    /// Numbers adjacent to no symbol at all.
+    pub fn isolated_numbers(&self) -> Vec<ParsedNumber> {
+        self.numbers_by_degree(|degree| degree == 0).into_iter().map(|(n, _)| n).collect()
+    }
+
+    /// For each symbol character, how many of its occurrences touch a given number of parts.
+    pub fn symbol_degree_distribution(&self) -> BTreeMap<char, BTreeMap<usize, usize>> {
+        let mut distribution: BTreeMap<char, BTreeMap<usize, usize>> = BTreeMap::new();
+        for i in self.graph.node_indices() {
+            if let PartNode::Symbol(s) = self.graph[i] {
+                *distribution.entry(s.symbol).or_default().entry(self.graph.neighbors(i).count()).or_insert(0) += 1;
+            }
+        }
+        distribution
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("graph parts {\n");
+        for i in self.graph.node_indices() {
+            let shape = match self.graph[i] {
+                PartNode::Number(_) => "box",
+                PartNode::Symbol(_) => "diamond",
+            };
+            let label = self.graph[i].to_string().replace('\\', "\\\\").replace('"', "\\\"");
+            dot.push_str(&format!("    {} [label=\"{label}\", shape={shape}];\n", i.index()));
+        }
+        for edge in self.graph.edge_references() {
+            dot.push_str(&format!("    {} -- {};\n", edge.source().index(), edge.target().index()));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
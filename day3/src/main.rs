@@ -2,7 +2,11 @@ use std::{env, fs::File, path::Path};
 use std::io::{self, BufRead};
 use regex::Regex;
 use lazy_static::lazy_static;
-use std::collections::HashMap;
+use unicode_segmentation::UnicodeSegmentation;
+
+mod annotate;
+mod graph;
+use std::collections::{HashMap, HashSet};
 
 
 lazy_static! {
@@ -10,7 +14,7 @@ lazy_static! {
     static ref SYMBOL_RE: Regex = Regex::new(r"([^\.\d])").unwrap();
 }
 
-#[derive(Clone, Copy)]
+#[derive(Hash, Eq, PartialEq, Clone, Copy)]
 struct ParsedNumber {
     number: u32,
     x_start: i32,
@@ -27,19 +31,127 @@ struct ParsedSymbol {
 
 type GearMap = HashMap<ParsedSymbol, Vec<ParsedNumber>>;
 
+enum SymbolSet {
+    Any,
+    Only(Vec<char>),
+}
+
+enum NeighbourCount {
+    Exactly(usize),
+    AtLeast(usize),
+}
+
+enum Aggregation {
+    Product,
+    Sum,
+    Max,
+}
+
+/// Whether numbers are aggregated per qualifying symbol, or once over every number
+/// touching at least one qualifying symbol.
+enum Scope {
+    PerSymbol,
+    Union,
+}
+
+/// Which symbols act as gears, how many numbers they need, and how those numbers
+/// combine into the final answer.
+struct GearRule {
+    symbols: SymbolSet,
+    neighbours: NeighbourCount,
+    aggregation: Aggregation,
+    scope: Scope,
+}
+
+impl GearRule {
+    /// Sum of all numbers adjacent to any symbol.
+    fn part1() -> Self {
+        GearRule { symbols: SymbolSet::Any, neighbours: NeighbourCount::AtLeast(1), aggregation: Aggregation::Sum, scope: Scope::Union }
+    }
+
+    /// Sum of the products of the two numbers around each `*`.
+    fn part2() -> Self {
+        GearRule { symbols: SymbolSet::Only(vec!['*']), neighbours: NeighbourCount::Exactly(2), aggregation: Aggregation::Product, scope: Scope::PerSymbol }
+    }
+
+    fn matches(&self, symbol: &ParsedSymbol, nb_neighbours: usize) -> bool {
+        let symbol_ok = match &self.symbols {
+            SymbolSet::Any => true,
+            SymbolSet::Only(chars) => chars.contains(&symbol.symbol),
+        };
+        let count_ok = match self.neighbours {
+            NeighbourCount::Exactly(n) => nb_neighbours == n,
+            NeighbourCount::AtLeast(n) => nb_neighbours >= n,
+        };
+        symbol_ok && count_ok
+    }
+
+    fn aggregate<'a, I>(&self, numbers: I) -> u64
+    where I: Iterator<Item = &'a ParsedNumber>, {
+        let values = numbers.map(|n| n.number as u64);
+        match self.aggregation {
+            Aggregation::Product => values.product(),
+            Aggregation::Sum => values.sum(),
+            Aggregation::Max => values.max().unwrap_or(0),
+        }
+    }
+}
+
+/// Parses a rule such as `symbols=#,neighbours=3+,aggregate=product,scope=symbol`.
+/// Unspecified fields keep their part 2 value.
+fn parse_rule(spec: &str) -> Result<GearRule, String> {
+    let mut rule = GearRule::part2();
+    for entry in spec.split(',').filter(|e| !e.is_empty()) {
+        let (key, value) = entry.split_once('=').ok_or(format!("Invalid rule entry: {entry}"))?;
+        match key {
+            "symbols" => rule.symbols = match value {
+                "any" => SymbolSet::Any,
+                chars => SymbolSet::Only(chars.chars().collect()),
+            },
+            "neighbours" => rule.neighbours = match value.strip_suffix('+') {
+                Some(n) => NeighbourCount::AtLeast(n.parse().map_err(|_| format!("Invalid neighbour count: {value}"))?),
+                None => NeighbourCount::Exactly(value.parse().map_err(|_| format!("Invalid neighbour count: {value}"))?),
+            },
+            "aggregate" => rule.aggregation = match value {
+                "product" => Aggregation::Product,
+                "sum" => Aggregation::Sum,
+                "max" => Aggregation::Max,
+                _ => return Err(format!("Unknown aggregation: {value}")),
+            },
+            "scope" => rule.scope = match value {
+                "symbol" => Scope::PerSymbol,
+                "union" => Scope::Union,
+                _ => return Err(format!("Unknown scope: {value}")),
+            },
+            _ => return Err(format!("Unknown rule field: {key}")),
+        }
+    }
+    Ok(rule)
+}
+
 fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
 where P: AsRef<Path>, {
     let file = File::open(filename)?;
     Ok(io::BufReader::new(file).lines())
 }
 
-fn parse_numbers(line: &str, line_number: i32) -> Vec<ParsedNumber> {
+/// Byte offset at which each grapheme cluster of `line` starts. Columns are indices
+/// into this list, so multi-byte symbols such as `€` occupy a single column.
+fn grapheme_starts(line: &str) -> Vec<usize> {
+    line.grapheme_indices(true).map(|(start, _)| start).collect()
+}
+
+fn column_of(starts: &[usize], byte_offset: usize) -> i32 {
+    starts.partition_point(|start| *start <= byte_offset) as i32 - 1
+}
+
+fn parse_numbers(line: &str, line_number: i32, starts: &[usize]) -> Vec<ParsedNumber> {
     let mut parsed = Vec::new();
     for capture in NUMBER_RE.captures_iter(line) {
         parsed.push(ParsedNumber {
             number: capture.get(1).unwrap().as_str().parse().unwrap(),
-            x_start: capture.get(1).unwrap().start() as i32,
-            x_end: capture.get(1).unwrap().end() as i32 - 1,
+            x_start: column_of(starts, capture.get(1).unwrap().start()),
+            x_end: column_of(starts, capture.get(1).unwrap().end() - 1),
             y: line_number
         })
     }
@@ -48,43 +160,79 @@ fn parse_numbers(line: &str, line_number: i32) -> Vec<ParsedNumber> {
 
 fn parse_symbols(line: &str, line_number: i32) -> Vec<ParsedSymbol> {
     let mut parsed = Vec::new();
-    for capture in SYMBOL_RE.captures_iter(line) {
-        parsed.push(ParsedSymbol {
-            symbol: capture.get(1).unwrap().as_str().chars().next().unwrap(),
-            x: capture.get(1).unwrap().start() as i32,
-            y: line_number
-        })
+    for (x, grapheme) in line.graphemes(true).enumerate() {
+        if SYMBOL_RE.is_match(grapheme) {
+            parsed.push(ParsedSymbol {
+                symbol: grapheme.chars().next().unwrap(),
+                x: x as i32,
+                y: line_number
+            })
+        }
     }
     parsed
 }
 
-fn is_adjacent(number: &ParsedNumber, symbols: &Vec<ParsedSymbol>) -> bool {
-    for symbol in symbols.iter() {
-        if
-            number.y >= symbol.y - 1
-            && number.y <= symbol.y + 1
-            && number.x_start <= symbol.x + 1
-            && number.x_end >= symbol.x - 1
-        {return true ;}
-    }
-    false
-}
-
-fn register_symbol(number: &ParsedNumber, gear_map: &mut GearMap, symbols: &Vec<ParsedSymbol>) {
-    for symbol in symbols.iter() {
-        if
-            number.y >= symbol.y - 1
-            && number.y <= symbol.y + 1
-            && number.x_start <= symbol.x + 1
-            && number.x_end >= symbol.x - 1
-        {
-            match gear_map.get_mut(&symbol) {
-                Some(list) => list.push(*number),
-                None => match gear_map.insert(*symbol, vec![*number]){
-                    None => (),
-                    Some(_) => panic!("Found value")
-                },
-            };
+/// Schematic parsed in a single pass, with symbols indexed by position so that
+/// adjacency lookups only look at the cells surrounding a number.
+struct Schematic {
+    numbers: Vec<ParsedNumber>,
+    symbols: Vec<ParsedSymbol>,
+    symbol_grid: Vec<Vec<Option<usize>>>,
+    lines: Vec<String>,
+}
+
+impl Schematic {
+    fn symbol_at(&self, x: i32, y: i32) -> Option<&ParsedSymbol> {
+        if x < 0 || y < 0 {
+            return None;
+        }
+        let index = (*self.symbol_grid.get(y as usize)?.get(x as usize)?)?;
+        Some(&self.symbols[index])
+    }
+
+    fn adjacent_symbols<'a>(&'a self, number: &ParsedNumber) -> impl Iterator<Item = &'a ParsedSymbol> + 'a {
+        let (x_start, x_end, y) = (number.x_start, number.x_end, number.y);
+        (y - 1..=y + 1).flat_map(move |sy| (x_start - 1..=x_end + 1).filter_map(move |sx| self.symbol_at(sx, sy)))
+    }
+}
+
+fn parse_schematic<I>(lines: I) -> Schematic
+where I: Iterator<Item = String>, {
+    let mut schematic = Schematic { numbers: Vec::new(), symbols: Vec::new(), symbol_grid: Vec::new(), lines: Vec::new() };
+    for (i, line) in lines.enumerate() {
+        let starts = grapheme_starts(&line);
+        let mut grid_row = vec![None; starts.len()];
+        for symbol in parse_symbols(&line, i as i32) {
+            grid_row[symbol.x as usize] = Some(schematic.symbols.len());
+            schematic.symbols.push(symbol);
+        }
+        schematic.symbol_grid.push(grid_row);
+        schematic.numbers.extend(parse_numbers(&line, i as i32, &starts));
+        schematic.lines.push(line);
+    }
+    schematic
+}
+
+fn register_symbol(number: &ParsedNumber, gear_map: &mut GearMap, schematic: &Schematic) {
+    for symbol in schematic.adjacent_symbols(number) {
+        gear_map.entry(*symbol).or_default().push(*number);
+    }
+}
+
+fn build_gear_map(schematic: &Schematic) -> GearMap {
+    let mut gear_map: GearMap = HashMap::new();
+    schematic.numbers.iter().for_each(|number| register_symbol(number, &mut gear_map, schematic));
+    gear_map
+}
+
+fn evaluate_rule(rule: &GearRule, schematic: &Schematic) -> u64 {
+    let gear_map = build_gear_map(schematic);
+    let gears = gear_map.iter().filter(|(symbol, numbers)| rule.matches(symbol, numbers.len()));
+    match rule.scope {
+        Scope::PerSymbol => gears.map(|(_, numbers)| rule.aggregate(numbers.iter())).sum(),
+        Scope::Union => {
+            let distinct: HashSet<&ParsedNumber> = gears.flat_map(|(_, numbers)| numbers.iter()).collect();
+            rule.aggregate(distinct.into_iter())
         }
     }
 }
@@ -92,13 +240,75 @@ fn register_symbol(number: &ParsedNumber, gear_map: &mut GearMap, symbols: &Vec<
 fn main() {
     let args: Vec<String> = env::args().collect();
     let filename = &args[1];
-    let all_symbols: Vec<ParsedSymbol> = read_lines(filename).unwrap().enumerate().map(|(i, line)| parse_symbols(&line.unwrap(), i as i32)).flatten().collect();
-    let all_numbers: Vec<ParsedNumber> = read_lines(filename).unwrap().enumerate().map(|(i, line)| parse_numbers(&line.unwrap(), i as i32)).flatten().collect();
-    // let sum: u32 = all_numbers.iter().filter(|number| is_adjacent(number, &all_symbols)).map(|number| number.number).sum();
-    // println!("Sum is: {sum}")
-    let mut gear_map: GearMap = HashMap::new();
-    let gear_symbols: Vec<ParsedSymbol> = all_symbols.into_iter().filter(|s| s.symbol == '*').collect();
-    all_numbers.iter().for_each(|number| register_symbol(number, &mut gear_map, &gear_symbols));
-    let gear_product_sum: u32 = gear_map.iter().filter(|(_, v)| v.len() == 2).map(|(_, v)| v[0].number * v[1].number).sum();
-    println!("Gear sum is: {gear_product_sum}")
+    let schematic = parse_schematic(read_lines(filename).unwrap().map(|line| line.unwrap()));
+    match args.get(2).map(|s| s.as_str()) {
+        Some("--rule") => {
+            let rule = parse_rule(&args[3]).unwrap();
+            let result = evaluate_rule(&rule, &schematic);
+            println!("Result is: {result}");
+        }
+        Some("--graph") => {
+            let part_graph = graph::PartGraph::from_schematic(&schematic);
+            let components = part_graph.components();
+            println!("Connected components: {}", components.len());
+            for component in components.iter().filter(|c| c.len() > 1) {
+                println!("  {}", component.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(", "));
+            }
+            for (number, degree) in part_graph.shared_numbers() {
+                println!("Shared number: {} @ ({}, {}) touches {degree} symbols", number.number, number.x_start, number.y);
+            }
+            for number in part_graph.isolated_numbers() {
+                println!("Isolated number: {} @ ({}, {})", number.number, number.x_start, number.y);
+            }
+            for (symbol, degrees) in part_graph.symbol_degree_distribution() {
+                let degrees: Vec<String> = degrees.iter().map(|(degree, count)| format!("{degree}: {count}")).collect();
+                println!("Symbol {symbol} degrees: {}", degrees.join(", "));
+            }
+        }
+        Some("--dot") => println!("{}", graph::PartGraph::from_schematic(&schematic).to_dot()),
+        Some("--annotate") => print!("{}", annotate::render(&schematic)),
+        Some("--csv") => annotate::write_csv(&schematic, &args[3]).unwrap(),
+        Some(option) => panic!("Unknown option: {option}"),
+        None => {
+            let sum = evaluate_rule(&GearRule::part1(), &schematic);
+            println!("Sum is: {sum}");
+            let gear_product_sum = evaluate_rule(&GearRule::part2(), &schematic);
+            println!("Gear sum is: {gear_product_sum}")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_str(schematic: &str) -> Schematic {
+        parse_schematic(schematic.lines().map(|l| l.to_string()))
+    }
+
+    #[test]
+    fn test_columns_after_multibyte_symbols() {
+        let schematic = parse_str("€.§.12\n.....€");
+        let twelve = schematic.numbers[0];
+        assert_eq!((twelve.x_start, twelve.x_end), (4, 5));
+        assert_eq!(schematic.symbols.iter().map(|s| (s.symbol, s.x)).collect::<Vec<_>>(), vec![('€', 0), ('§', 2), ('€', 5)]);
+        assert!(schematic.adjacent_symbols(&twelve).next().is_some());
+    }
+
+    #[test]
+    fn test_non_ascii_gears() {
+        let schematic = parse_str("12.€.\n..§34\n7....");
+        assert_eq!(evaluate_rule(&GearRule::part1(), &schematic), 46);
+        let rule = parse_rule("symbols=§,neighbours=2").unwrap();
+        assert_eq!(evaluate_rule(&rule, &schematic), 12 * 34);
+    }
+
+    #[test]
+    fn test_combining_mark_is_one_column() {
+        // "e" followed by a combining acute accent renders as a single character
+        let schematic = parse_str("e\u{301}..5\n.....");
+        assert_eq!(schematic.symbols.len(), 1);
+        assert_eq!(schematic.numbers[0].x_start, 3);
+        assert!(schematic.adjacent_symbols(&schematic.numbers[0]).next().is_none());
+    }
 }
@@ -2,18 +2,49 @@ use std::{env, fs::File, path::Path};
 use std::io::{self, BufRead};
 use regex::Regex;
 use lazy_static::lazy_static;
-use std::collections::HashSet;
+
+mod provenance;
+mod streaming;
 
 
 lazy_static! {
     static ref CARD_RE: Regex = Regex::new(r"^Card +(\d+): ([\d ]+) \| ([\d ]+)$").unwrap();
 }
 
-// #[derive(Clone)]
+/// Largest card number that fits in a `NumberSet`.
+const MAX_NUMBER: usize = 1024;
+
+/// Fixed-width bitset of card numbers, so matching is an AND and a popcount.
+#[derive(Clone, Copy)]
+struct NumberSet([u64; MAX_NUMBER / 64 + 1]);
+
+impl NumberSet {
+    fn new() -> Self {
+        NumberSet([0; MAX_NUMBER / 64 + 1])
+    }
+
+    /// Adds `number`, returning whether it was not already present.
+    fn insert(&mut self, number: u32) -> Result<bool, String> {
+        if number as usize > MAX_NUMBER {
+            return Err(format!("Card number {number} is out of range (max {MAX_NUMBER})"));
+        }
+        let (word, bit) = (number as usize / 64, 1 << (number % 64));
+        let is_new = self.0[word] & bit == 0;
+        self.0[word] |= bit;
+        Ok(is_new)
+    }
+
+    fn intersection_count(&self, other: &Self) -> u32 {
+        self.0.iter().zip(other.0.iter()).map(|(a, b)| (a & b).count_ones()).sum()
+    }
+}
+
 struct ParsedCard {
     card_number: u32,
-    winning_numbers: Vec<u32>,
-    registered_numbers: Vec<u32>,
+    winning_numbers: NumberSet,
+    registered_numbers: NumberSet,
+    duplicate_winning: Vec<u32>,
+    duplicate_registered: Vec<u32>,
 }
 
 fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
@@ -22,49 +53,232 @@ where P: AsRef<Path>, {
     Ok(io::BufReader::new(file).lines())
 }
 
-fn parse_card(line: &str) -> ParsedCard {
-    fn parse_numbers(number_list: &str) -> Vec<u32>{
-        number_list.split(" ").filter(|s| !s.is_empty()).map(|substring| substring.parse::<u32>().unwrap()).collect()
+fn parse_card(line: &str) -> Result<ParsedCard, String> {
+    fn parse_numbers(number_list: &str) -> Result<(NumberSet, Vec<u32>), String> {
+        let mut set = NumberSet::new();
+        let mut duplicates = Vec::new();
+        for number in number_list.split(' ').filter(|s| !s.is_empty()).map(|substring| substring.parse::<u32>().unwrap()) {
+            if !set.insert(number)? {
+                duplicates.push(number);
+            }
+        }
+        Ok((set, duplicates))
     }
     let captured = CARD_RE.captures(line).unwrap();
-    ParsedCard {
+    let (winning_numbers, duplicate_winning) = parse_numbers(captured.get(2).unwrap().as_str())?;
+    let (registered_numbers, duplicate_registered) = parse_numbers(captured.get(3).unwrap().as_str())?;
+    Ok(ParsedCard {
         card_number: captured.get(1).unwrap().as_str().parse().unwrap(),
-        winning_numbers: parse_numbers(captured.get(2).unwrap().as_str()),
-        registered_numbers: parse_numbers(captured.get(3).unwrap().as_str()),
+        winning_numbers,
+        registered_numbers,
+        duplicate_winning,
+        duplicate_registered,
+    })
+}
+
+/// Problems with a card that would skew the results: card numbers that are not
+/// consecutive from 1, which the copy cascade relies on, and numbers listed twice.
+/// `expected` is the card number following the previous card, so a gap is reported once.
+fn validate_card(card: &ParsedCard, line_number: usize, expected: u32) -> Vec<String> {
+    fn format_numbers(numbers: &[u32]) -> String {
+        numbers.iter().map(|n| n.to_string()).collect::<Vec<_>>().join(", ")
+    }
+    let mut issues = Vec::new();
+    if card.card_number != expected {
+        issues.push(format!("Line {line_number}: expected card {expected}, found card {}", card.card_number));
+    }
+    if !card.duplicate_winning.is_empty() {
+        issues.push(format!("Line {line_number}: card {} has duplicate winning numbers: {}", card.card_number, format_numbers(&card.duplicate_winning)));
     }
+    if !card.duplicate_registered.is_empty() {
+        issues.push(format!("Line {line_number}: card {} has duplicate registered numbers: {}", card.card_number, format_numbers(&card.duplicate_registered)));
+    }
+    issues
 }
 
 
 fn count_card(card: &ParsedCard) -> u32 {
-    let winning: HashSet<u32> = HashSet::from_iter(card.winning_numbers.clone());
-    let registered: HashSet<u32> = HashSet::from_iter(card.registered_numbers.clone());
-    winning.intersection(&registered).count() as u32
+    card.winning_numbers.intersection_count(&card.registered_numbers)
 }
 
-fn score_card(card: &ParsedCard) -> u32 {
-    let nb_intersect = count_card(card);
+/// Part 1 points of a card, saturating for cards with more than 64 matches.
+fn score_card(nb_intersect: u32) -> u64 {
     if nb_intersect > 0 {
-        1 << (nb_intersect - 1)
+        1u64.checked_shl(nb_intersect - 1).unwrap_or(u64::MAX)
     } else {
         0
     }
 }
 
-fn increment_next(counters: &mut Vec<u32>, position: usize, number: u32) {
-    for i in 0..(number as usize) {
-        counters[position + i + 1] += counters[position];
+/// What to do with copies won past the last card of the table.
+#[derive(Clone, Copy)]
+enum Overflow {
+    /// Drop the copies that would land past the end.
+    Clamp,
+    /// Refuse the table.
+    Error,
+    /// Continue from the first card. Only copies landing after the winning card are
+    /// cascaded; those landing on the card itself or on earlier cards are not.
+    Wrap,
+}
+
+fn parse_overflow(name: &str) -> Result<Overflow, String> {
+    match name {
+        "clamp" => Ok(Overflow::Clamp),
+        "error" => Ok(Overflow::Error),
+        "wrap" => Ok(Overflow::Wrap),
+        _ => Err(format!("Unknown overflow mode: {name}")),
     }
 }
 
+fn overflow_error(card_number: u32, number: u32, following: usize) -> String {
+    format!("Card {card_number} wins copies of the next {number} cards, but only {following} cards follow it")
+}
+
+/// Indices of the cards that receive copies from the card at `position`.
+fn copy_targets(position: usize, number: u32, nb_cards: usize, overflow: Overflow) -> impl Iterator<Item = usize> {
+    (position + 1..=position + number as usize).filter_map(move |target| match overflow {
+        _ if target < nb_cards => Some(target),
+        Overflow::Wrap => Some(target % nb_cards),
+        Overflow::Clamp | Overflow::Error => None,
+    })
+}
+
+fn increment_next(counters: &mut [u64], position: usize, number: u32, card_number: u32, overflow: Overflow) -> Result<(), String> {
+    let nb_cards = counters.len();
+    let last_target = position + number as usize;
+    if last_target >= nb_cards {
+        if let Overflow::Error = overflow {
+            return Err(overflow_error(card_number, number, nb_cards - position - 1));
+        }
+    }
+    let copies = counters[position];
+    for target in copy_targets(position, number, nb_cards, overflow) {
+        counters[target] += copies;
+    }
+    Ok(())
+}
+
 
 fn main() {
     let args: Vec<String> = env::args().collect();
     let filename = &args[1];
-    let parsed: Vec<ParsedCard> = read_lines(filename).unwrap().map(|line| parse_card(&line.unwrap())).collect();
-    let mut counters: Vec<u32> = vec![1; parsed.len()];
-    // let result: u32 = parsed.iter().map(score_card).sum();
-    // println!("Total score: {result}");
-    parsed.iter().enumerate().for_each(|(i, card)| increment_next(&mut counters, i, count_card(card)));
-    let result: u32 = counters.iter().sum();
-    println!("Total cards: {result}");
+    let mut overflow = Overflow::Error;
+    let mut strict = false;
+    let mut stream = false;
+    let mut output: Option<&str> = None;
+    let mut options = args[2..].iter();
+    while let Some(option) = options.next() {
+        match option.as_str() {
+            "--overflow" => overflow = parse_overflow(options.next().unwrap()).unwrap(),
+            "--strict" => strict = true,
+            "--stream" => stream = true,
+            "--report" | "--dot" | "--json" => output = Some(option),
+            _ => panic!("Unknown option: {option}"),
+        }
+    }
+    if stream {
+        match streaming::stream_cards(read_lines(filename).unwrap().map(|line| line.unwrap()), overflow, strict) {
+            Ok(totals) => {
+                println!("Total score: {}", totals.score);
+                println!("Total cards: {}", totals.cards);
+            }
+            Err(e) => {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    let mut nb_issues = 0;
+    let mut previous_card = 0;
+    let matches: Vec<(u32, u32)> = read_lines(filename).unwrap().enumerate().map(|(i, line)| {
+        let card = parse_card(&line.unwrap()).unwrap_or_else(|e| {
+            eprintln!("Line {}: {e}", i + 1);
+            std::process::exit(1);
+        });
+        for issue in validate_card(&card, i + 1, previous_card + 1) {
+            eprintln!("{issue}");
+            nb_issues += 1;
+        }
+        previous_card = card.card_number;
+        (card.card_number, count_card(&card))
+    }).collect();
+    if strict && nb_issues > 0 {
+        eprintln!("Rejecting input with {nb_issues} validation issues");
+        std::process::exit(1);
+    }
+    let mut counters: Vec<u64> = vec![1; matches.len()];
+    let mut cascade = output.map(|_| provenance::CopyCascade::new(&matches));
+    for (i, (card_number, nb_matches)) in matches.iter().enumerate() {
+        if let Some(cascade) = cascade.as_mut() {
+            cascade.record(i, counters[i], copy_targets(i, *nb_matches, counters.len(), overflow));
+        }
+        if let Err(e) = increment_next(&mut counters, i, *nb_matches, *card_number, overflow) {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    }
+
+    if let Some(cascade) = cascade.as_mut() {
+        cascade.set_copies(&counters);
+    }
+
+    match (output, cascade) {
+        (Some("--report"), Some(cascade)) => print!("{}", cascade.report()),
+        (Some("--dot"), Some(cascade)) => print!("{}", cascade.to_dot()),
+        (Some("--json"), Some(cascade)) => println!("{}", cascade.to_json()),
+        _ => {
+            let result = matches.iter().fold(0u64, |total, (_, m)| total.saturating_add(score_card(*m)));
+            println!("Total score: {result}");
+            let result: u64 = counters.iter().sum();
+            println!("Total cards: {result}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_score_card_many_matches() {
+        let numbers: Vec<String> = (1..=40).map(|n| n.to_string()).collect();
+        let card = parse_card(&format!("Card 1: {0} | {0}", numbers.join(" "))).unwrap();
+        assert_eq!(count_card(&card), 40);
+        assert_eq!(score_card(count_card(&card)), 1 << 39);
+        assert_eq!(score_card(64), 1 << 63);
+        assert_eq!(score_card(65), u64::MAX);
+    }
+
+    #[test]
+    fn test_copies_beyond_u32() {
+        let lines: Vec<String> = (1..=60).map(|i| format!("Card {i}: 1 2 | 1 2")).collect();
+        let mut counters = vec![1; lines.len()];
+        for (i, line) in lines.iter().enumerate() {
+            let card = parse_card(line).unwrap();
+            increment_next(&mut counters, i, count_card(&card), card.card_number, Overflow::Clamp).unwrap();
+        }
+        let totals = streaming::stream_cards(lines.into_iter(), Overflow::Clamp, true).unwrap();
+        assert_eq!(counters.iter().sum::<u64>(), totals.cards);
+        assert_eq!(totals.cards, 10610209857660);
+    }
+
+    fn cascade(lines: &[&str], overflow: Overflow) -> Result<Vec<u64>, String> {
+        let mut counters = vec![1; lines.len()];
+        for (i, line) in lines.iter().enumerate() {
+            let card = parse_card(line).unwrap();
+            increment_next(&mut counters, i, count_card(&card), card.card_number, overflow)?;
+        }
+        Ok(counters)
+    }
+
+    #[test]
+    fn test_overflow_modes() {
+        let lines = ["Card 1: 1 2 3 | 1 2 3", "Card 2: 5 | 6"];
+        assert_eq!(cascade(&lines, Overflow::Error), Err(overflow_error(1, 3, 1)));
+        assert_eq!(cascade(&lines, Overflow::Clamp), Ok(vec![1, 2]));
+        // Card 1 wins copies of cards 2, 1 and 2; its own copy is not cascaded
+        assert_eq!(cascade(&lines, Overflow::Wrap), Ok(vec![2, 3]));
+    }
 }
@@ -0,0 +1,90 @@
+use crate::score_card;
+
+
+struct CardSummary {
+    card_number: u32,
+    matches: u32,
+    copies: u64,
+}
+
+/// One instance of a card winning copies of a later one.
+struct CopyEdge {
+    from: usize,
+    to: usize,
+    copies: u64,
+}
+
+/// Which cards produced copies of which, recorded while the cascade runs.
+pub struct CopyCascade {
+    cards: Vec<CardSummary>,
+    edges: Vec<CopyEdge>,
+}
+
+impl CopyCascade {
+    pub fn new(matches: &[(u32, u32)]) -> Self {
+        let cards = matches.iter().map(|(card_number, nb_matches)| CardSummary { card_number: *card_number, matches: *nb_matches, copies: 1 }).collect();
+        CopyCascade { cards, edges: Vec::new() }
+    }
+
+    /// Records that each of the `copies` instances of card `from` won a copy of every target.
+    pub fn record<I>(&mut self, from: usize, copies: u64, targets: I)
+    where I: Iterator<Item = usize>, {
+        self.edges.extend(targets.map(|to| CopyEdge { from, to, copies }));
+    }
+
+    pub fn set_copies(&mut self, counters: &[u64]) {
+        self.cards.iter_mut().zip(counters.iter()).for_each(|(card, copies)| card.copies = *copies);
+    }
+
+    /// Per-card table of copies, matches, part 1 points, copies won for other cards and
+    /// share of the final total.
+    pub fn report(&self) -> String {
+        let total: u64 = self.cards.iter().map(|c| c.copies).sum();
+        let mut won = vec![0; self.cards.len()];
+        self.edges.iter().for_each(|edge| won[edge.from] += edge.copies);
+
+        let mut report = format!("{:>6} {:>20} {:>8} {:>20} {:>20} {:>8}\n", "card", "copies", "matches", "points", "won", "share");
+        for (card, won) in self.cards.iter().zip(won.iter()) {
+            report.push_str(&format!(
+                "{:>6} {:>20} {:>8} {:>20} {:>20} {:>7.2}%\n",
+                card.card_number,
+                card.copies,
+                card.matches,
+                score_card(card.matches),
+                won,
+                100.0 * card.copies as f64 / total as f64,
+            ));
+        }
+        report.push_str(&format!("Total cards: {total}\n"));
+        report
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph cascade {\n");
+        for card in self.cards.iter() {
+            dot.push_str(&format!("    card{0} [label=\"Card {0}\\n{1} matches\"];\n", card.card_number, card.matches));
+        }
+        for edge in self.edges.iter() {
+            dot.push_str(&format!("    card{} -> card{} [label=\"{}\"];\n", self.cards[edge.from].card_number, self.cards[edge.to].card_number, edge.copies));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    pub fn to_json(&self) -> String {
+        let cards: Vec<String> = self.cards.iter().map(|card| format!(
+            "{{\"card\":{},\"matches\":{},\"points\":{},\"copies\":{}}}",
+            card.card_number,
+            card.matches,
+            score_card(card.matches),
+            card.copies,
+        )).collect();
+        let edges: Vec<String> = self.edges.iter().map(|edge| format!(
+            "{{\"from\":{},\"to\":{},\"copies\":{}}}",
+            self.cards[edge.from].card_number,
+            self.cards[edge.to].card_number,
+            edge.copies,
+        )).collect();
+        format!("{{\"cards\":[{}],\"edges\":[{}]}}", cards.join(","), edges.join(","))
+    }
+}
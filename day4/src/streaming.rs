@@ -0,0 +1,106 @@
+use std::collections::VecDeque;
+
+use crate::{count_card, overflow_error, parse_card, score_card, validate_card, Overflow};
+
+
+pub struct StreamTotals {
+    pub score: u64,
+    pub cards: u64,
+}
+
+/// A card whose copies may still run past the end of the table, `reach` being the
+/// index of the last card it wins a copy of.
+struct OpenCard {
+    card_number: u32,
+    position: usize,
+    matches: u32,
+    reach: usize,
+}
+
+/// Computes both parts one line at a time. Only copies won for the next few cards are
+/// kept, so memory is bounded by the largest number of matches rather than the number
+/// of cards. Wrapping needs the whole table and is therefore not supported here.
+pub fn stream_cards<I>(lines: I, overflow: Overflow, strict: bool) -> Result<StreamTotals, String>
+where I: Iterator<Item = String>, {
+    if let Overflow::Wrap = overflow {
+        return Err("Wrap overflow is not supported in streaming mode".to_string());
+    }
+    let mut totals = StreamTotals { score: 0, cards: 0 };
+    let mut pending: VecDeque<u64> = VecDeque::new();
+    // Cards that could still overflow, ordered by position with increasing reach. A card
+    // reaching no further than an earlier one would never be the first to overflow.
+    let mut open_cards: VecDeque<OpenCard> = VecDeque::new();
+    let mut nb_cards = 0;
+    let mut previous_card = 0;
+
+    for (i, line) in lines.enumerate() {
+        let card = parse_card(&line).map_err(|e| format!("Line {}: {e}", i + 1))?;
+        for issue in validate_card(&card, i + 1, previous_card + 1) {
+            eprintln!("{issue}");
+            if strict {
+                return Err("Rejecting input with validation issues".to_string());
+            }
+        }
+        previous_card = card.card_number;
+        let nb_matches = count_card(&card);
+        let copies = 1 + pending.pop_front().unwrap_or(0);
+        totals.score = totals.score.saturating_add(score_card(nb_matches));
+        totals.cards += copies;
+
+        if pending.len() < nb_matches as usize {
+            pending.resize(nb_matches as usize, 0);
+        }
+        pending.iter_mut().take(nb_matches as usize).for_each(|p| *p += copies);
+
+        while open_cards.front().is_some_and(|open| open.reach <= i) {
+            open_cards.pop_front();
+        }
+        let reach = i + nb_matches as usize;
+        if open_cards.back().map_or(reach > i, |open| reach > open.reach) {
+            open_cards.push_back(OpenCard { card_number: card.card_number, position: i, matches: nb_matches, reach });
+        }
+        nb_cards = i + 1;
+    }
+
+    if let Overflow::Error = overflow {
+        if let Some(open) = open_cards.iter().find(|open| open.reach >= nb_cards) {
+            return Err(overflow_error(open.card_number, open.matches, nb_cards - open.position - 1));
+        }
+    }
+    Ok(totals)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53
+Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19
+Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1
+Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83
+Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36
+Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11";
+
+    #[test]
+    fn test_stream_cards_example() {
+        let totals = stream_cards(EXAMPLE.lines().map(|l| l.to_string()), Overflow::Error, true).unwrap();
+        assert_eq!((totals.score, totals.cards), (13, 30));
+    }
+
+    #[test]
+    fn test_stream_cards_overflow() {
+        let lines = ["Card 1: 1 2 3 | 1 2 3", "Card 2: 4 5 | 4 5", "Card 3: 6 | 7"];
+        let error = stream_cards(lines.iter().map(|l| l.to_string()), Overflow::Error, false).err().unwrap();
+        assert_eq!(error, overflow_error(1, 3, 2));
+        let totals = stream_cards(lines.iter().map(|l| l.to_string()), Overflow::Clamp, false).unwrap();
+        assert_eq!(totals.cards, 1 + 2 + 4);
+    }
+
+    #[test]
+    fn test_stream_cards_number_range() {
+        let totals = stream_cards(["Card 1: 1023 1024 | 1024".to_string()].into_iter(), Overflow::Clamp, true).unwrap();
+        assert_eq!(totals.score, 1);
+        let error = stream_cards(["Card 1: 1025 | 1".to_string()].into_iter(), Overflow::Clamp, true).err().unwrap();
+        assert_eq!(error, "Line 1: Card number 1025 is out of range (max 1024)");
+    }
+}
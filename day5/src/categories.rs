@@ -0,0 +1,157 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::BasicMappingCollection;
+
+
+/// One `from-to-to map:` section of the almanac.
+pub struct Section {
+    pub from: String,
+    pub to: String,
+    pub mappings: BasicMappingCollection,
+    /// Input line number of each mapping.
+    pub lines: Vec<usize>,
+}
+
+/// Categories linked by the almanac sections, so that numbers can be mapped between any
+/// two categories along the path that connects them.
+pub struct CategoryGraph {
+    sections: Vec<Section>,
+    outgoing: HashMap<String, Vec<usize>>,
+}
+
+impl CategoryGraph {
+    /// Builds the graph, rejecting duplicate links, sections whose source category has
+    /// not been reached by an earlier section, and cycles.
+    pub fn new(sections: Vec<Section>, root: &str) -> Result<Self, Vec<String>> {
+        let mut errors = Vec::new();
+        let mut outgoing: HashMap<String, Vec<usize>> = HashMap::new();
+        let mut reached: HashSet<&str> = HashSet::from([root]);
+        for (i, section) in sections.iter().enumerate() {
+            if !reached.contains(section.from.as_str()) {
+                errors.push(format!("Section {}-to-{} comes before any section mapping to {}", section.from, section.to, section.from));
+            }
+            reached.insert(&section.to);
+            let links = outgoing.entry(section.from.clone()).or_default();
+            if links.iter().any(|j| sections[*j].to == section.to) {
+                errors.push(format!("Duplicate section {}-to-{}", section.from, section.to));
+            }
+            links.push(i);
+        }
+
+        let graph = CategoryGraph { sections, outgoing };
+        if let Some(cycle) = graph.find_cycle() {
+            errors.push(format!("Cycle between categories: {}", cycle.join(" -> ")));
+        }
+        if errors.is_empty() {
+            Ok(graph)
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn find_cycle(&self) -> Option<Vec<String>> {
+        fn visit<'a>(graph: &'a CategoryGraph, category: &'a str, path: &mut Vec<&'a str>, done: &mut HashSet<&'a str>) -> Option<Vec<String>> {
+            if let Some(start) = path.iter().position(|c| *c == category) {
+                let mut cycle: Vec<String> = path[start..].iter().map(|c| c.to_string()).collect();
+                cycle.push(category.to_string());
+                return Some(cycle);
+            }
+            if done.contains(category) {
+                return None;
+            }
+            path.push(category);
+            for i in graph.outgoing.get(category).into_iter().flatten() {
+                if let Some(cycle) = visit(graph, &graph.sections[*i].to, path, done) {
+                    return Some(cycle);
+                }
+            }
+            path.pop();
+            done.insert(category);
+            None
+        }
+        let mut done = HashSet::new();
+        self.sections.iter().find_map(|section| visit(self, &section.from, &mut Vec::new(), &mut done))
+    }
+
+    /// Mapping layers to go from `from` to `to`, along the shortest path between them.
+    pub fn chain(&self, from: &str, to: &str) -> Result<Vec<BasicMappingCollection>, String> {
+        Ok(self.path(from, to)?.into_iter().map(|section| section.mappings.clone()).collect())
+    }
+
+    /// Sections along the shortest path from `from` to `to`.
+    pub fn path(&self, from: &str, to: &str) -> Result<Vec<&Section>, String> {
+        let mut previous: HashMap<&str, usize> = HashMap::new();
+        let mut queue = VecDeque::from([from]);
+        while let Some(category) = queue.pop_front() {
+            if category == to {
+                let mut path = Vec::new();
+                let mut current = to;
+                while current != from {
+                    let section = &self.sections[previous[current]];
+                    path.push(section);
+                    current = &section.from;
+                }
+                path.reverse();
+                return Ok(path);
+            }
+            for i in self.outgoing.get(category).into_iter().flatten() {
+                let next = self.sections[*i].to.as_str();
+                if next != from && !previous.contains_key(next) {
+                    previous.insert(next, *i);
+                    queue.push_back(next);
+                }
+            }
+        }
+        Err(format!("No path from {from} to {to}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sections(links: &[(&str, &str)]) -> Vec<Section> {
+        links.iter().map(|(from, to)| Section {
+            from: from.to_string(),
+            to: to.to_string(),
+            mappings: BasicMappingCollection { ranges: Vec::new() },
+            lines: Vec::new(),
+        }).collect()
+    }
+
+    #[test]
+    fn test_out_of_order_sections() {
+        let errors = CategoryGraph::new(sections(&[("soil", "fertilizer"), ("seed", "soil")]), "seed").err().unwrap();
+        assert_eq!(errors, vec!["Section soil-to-fertilizer comes before any section mapping to soil"]);
+    }
+
+    #[test]
+    fn test_duplicate_sections_and_cycles() {
+        let links = [("seed", "soil"), ("soil", "fertilizer"), ("fertilizer", "soil"), ("seed", "soil")];
+        let errors = CategoryGraph::new(sections(&links), "seed").err().unwrap();
+        assert_eq!(errors, vec!["Duplicate section seed-to-soil", "Cycle between categories: soil -> fertilizer -> soil"]);
+    }
+
+    #[test]
+    fn test_missing_link() {
+        let graph = CategoryGraph::new(sections(&[("seed", "soil"), ("soil", "fertilizer")]), "seed").ok().unwrap();
+        assert_eq!(graph.path("seed", "location").err(), Some("No path from seed to location".to_string()));
+    }
+
+    #[test]
+    fn test_shortest_path() {
+        let links = [
+            ("seed", "soil"),
+            ("soil", "fertilizer"),
+            ("soil", "water"),
+            ("fertilizer", "water"),
+            ("water", "light"),
+            ("light", "temperature"),
+            ("temperature", "humidity"),
+            ("humidity", "location"),
+        ];
+        let graph = CategoryGraph::new(sections(&links), "seed").ok().unwrap();
+        let path: Vec<(&str, &str)> = graph.path("soil", "humidity").unwrap().iter().map(|s| (s.from.as_str(), s.to.as_str())).collect();
+        assert_eq!(path, vec![("soil", "water"), ("water", "light"), ("light", "temperature"), ("temperature", "humidity")]);
+    }
+}
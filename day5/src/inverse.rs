@@ -0,0 +1,71 @@
+use crate::range_set::RangeSet;
+use crate::{BasicMapping, BasicMappingCollection, BasicRange};
+
+
+impl BasicMappingCollection {
+    /// Collection sending each number back to the numbers that lead to it. Identity gaps
+    /// are listed explicitly, since there is no fallthrough once origins and destinations
+    /// are swapped. Several origins can share a destination, so the inverted ranges may
+    /// overlap and must be applied with `map_ranges_through_all`.
+    pub fn invert(&self) -> BasicMappingCollection {
+        let ranges = self.pieces().into_iter().map(|piece| BasicMapping { origin: piece.destination, destination: piece.origin }).collect();
+        BasicMappingCollection { ranges }
+    }
+
+    /// Image of `ranges` through every mapping whose origin they touch, with no identity
+    /// fallthrough for numbers outside all origins.
+    pub fn map_ranges_through_all(&self, ranges: &RangeSet) -> RangeSet {
+        let mut mapped = Vec::new();
+        for mapping in self.ranges.iter() {
+            for common in ranges.intersection(&RangeSet::from(mapping.origin.clone())).iter() {
+                mapped.push(BasicRange {
+                    range_start: mapping.destination.range_start + (common.range_start - mapping.origin.range_start),
+                    range_end: mapping.destination.range_start + (common.range_end - mapping.origin.range_start),
+                });
+            }
+        }
+        RangeSet::from_ranges(mapped)
+    }
+}
+
+/// Seeds leading to any of `locations`, running the inverted chain from the last layer.
+pub fn seeds_for_locations(chain: &[BasicMappingCollection], locations: RangeSet) -> RangeSet {
+    chain.iter().rev().fold(locations, |ranges, collection| collection.invert().map_ranges_through_all(&ranges))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MappingCollection;
+
+    #[test]
+    fn test_inverse_of_many_to_one() {
+        // 10..12 is sent onto 0..2, which its own identity gap also covers
+        let collection = BasicMappingCollection {
+            ranges: vec![BasicMapping {
+                origin: BasicRange { range_start: 10, range_end: 12 },
+                destination: BasicRange { range_start: 0, range_end: 2 },
+            }],
+        };
+        let seeds = seeds_for_locations(&[collection], RangeSet::from(BasicRange { range_start: 1, range_end: 2 }));
+        let expected = RangeSet::from_ranges(vec![BasicRange { range_start: 1, range_end: 2 }, BasicRange { range_start: 11, range_end: 12 }]);
+        assert_eq!(seeds, expected);
+    }
+
+    #[test]
+    fn test_inverse_round_trip() {
+        let collection = BasicMappingCollection {
+            ranges: vec![
+                BasicMapping { origin: BasicRange { range_start: 98, range_end: 100 }, destination: BasicRange { range_start: 50, range_end: 52 } },
+                BasicMapping { origin: BasicRange { range_start: 50, range_end: 98 }, destination: BasicRange { range_start: 52, range_end: 100 } },
+            ],
+        };
+        for location in 0..120 {
+            let seeds = seeds_for_locations(std::slice::from_ref(&collection), RangeSet::from(BasicRange { range_start: location, range_end: location + 1 }));
+            for seed in seeds.iter().flat_map(|r| r.range_start..r.range_end) {
+                assert_eq!(collection.get_mapped_from_origin(seed), location);
+            }
+            assert!(!seeds.is_empty());
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use crate::categories::Section;
+use crate::range_set::RangeSet;
+use crate::{BasicRange, Range};
+
+
+/// Part of a range as it goes through one section, and what it splits into afterwards.
+pub struct LineageNode {
+    section: String,
+    /// Input line of the mapping that handled the range, `None` when no mapping did and
+    /// the numbers were left as they were.
+    line: Option<usize>,
+    input: BasicRange,
+    output: BasicRange,
+    children: Vec<LineageNode>,
+}
+
+/// Every sub-range an initial seed range splits into, layer after layer.
+pub struct SeedLineage {
+    seed: BasicRange,
+    children: Vec<LineageNode>,
+}
+
+/// Cuts `range` into the parts handled by each mapping of `section`, in input order.
+/// Earlier mappings win on overlap, like `get_mapped_from_origin`.
+fn split_range(section: &Section, range: &BasicRange) -> Vec<(Option<usize>, BasicRange, BasicRange)> {
+    let mut remaining = RangeSet::from(range.clone());
+    let mut parts = Vec::new();
+    for (mapping, line) in section.mappings.ranges.iter().zip(section.lines.iter()) {
+        let origin = RangeSet::from(mapping.origin.clone());
+        for common in remaining.intersection(&origin).iter() {
+            let output = BasicRange {
+                range_start: mapping.destination.range_start + mapping.origin.offset(common.range_start),
+                range_end: mapping.destination.range_start + mapping.origin.offset(common.range_end),
+            };
+            parts.push((Some(*line), common.clone(), output));
+        }
+        remaining = remaining.difference(&origin);
+    }
+    parts.extend(remaining.iter().map(|r| (None, r.clone(), r.clone())));
+    parts.sort_by_key(|(_, input, _)| input.range_start);
+    parts
+}
+
+fn trace_range(path: &[&Section], range: &BasicRange) -> Vec<LineageNode> {
+    let Some((section, rest)) = path.split_first() else {
+        return Vec::new();
+    };
+    split_range(section, range).into_iter().map(|(line, input, output)| LineageNode {
+        section: format!("{}-to-{}", section.from, section.to),
+        line,
+        children: trace_range(rest, &output),
+        input,
+        output,
+    }).collect()
+}
+
+pub fn trace(path: &[&Section], seeds: &[BasicRange]) -> Vec<SeedLineage> {
+    seeds.iter().map(|seed| SeedLineage { seed: seed.clone(), children: trace_range(path, seed) }).collect()
+}
+
+impl LineageNode {
+    /// Lowest final number reachable from this node, and the number at this node's
+    /// input that leads to it.
+    fn minimum(&self) -> (u64, u64) {
+        let (location, output_value) = self.children.iter().map(|c| c.minimum()).min().unwrap_or((self.output.range_start, self.output.range_start));
+        (location, self.input.range_start + self.output.offset(output_value))
+    }
+
+    fn handler(&self) -> String {
+        match self.line {
+            Some(line) => format!("line {line}"),
+            None => "identity".to_string(),
+        }
+    }
+
+    fn render_text(&self, depth: usize, text: &mut String) {
+        text.push_str(&format!(
+            "{}{} {}: {}..{} -> {}..{}\n",
+            "  ".repeat(depth),
+            self.section,
+            self.handler(),
+            self.input.range_start,
+            self.input.range_end,
+            self.output.range_start,
+            self.output.range_end,
+        ));
+        self.children.iter().for_each(|c| c.render_text(depth + 1, text));
+    }
+
+    fn to_json(&self) -> String {
+        format!(
+            "{{\"section\":\"{}\",\"mapping_line\":{},\"input\":[{},{}],\"output\":[{},{}],\"children\":[{}]}}",
+            self.section,
+            self.line.map_or("null".to_string(), |l| l.to_string()),
+            self.input.range_start,
+            self.input.range_end,
+            self.output.range_start,
+            self.output.range_end,
+            self.children.iter().map(|c| c.to_json()).collect::<Vec<_>>().join(","),
+        )
+    }
+}
+
+impl SeedLineage {
+    fn minimum(&self) -> (u64, u64) {
+        self.children.iter().map(|c| c.minimum()).min().unwrap_or((self.seed.range_start, self.seed.range_start))
+    }
+}
+
+/// Final minimum over all the lineages, with the seed and seed range it comes from.
+fn overall_minimum(lineages: &[SeedLineage]) -> Option<(u64, u64, &BasicRange)> {
+    lineages.iter().map(|l| {
+        let (location, seed) = l.minimum();
+        (location, seed, &l.seed)
+    }).min_by_key(|(location, seed, _)| (*location, *seed))
+}
+
+pub fn render_text(lineages: &[SeedLineage]) -> String {
+    let mut text = String::new();
+    for lineage in lineages.iter() {
+        text.push_str(&format!("seed {}..{}\n", lineage.seed.range_start, lineage.seed.range_end));
+        lineage.children.iter().for_each(|c| c.render_text(1, &mut text));
+    }
+    if let Some((location, seed, seed_range)) = overall_minimum(lineages) {
+        text.push_str(&format!("Minimum {location} from seed {seed} (seed range {}..{})\n", seed_range.range_start, seed_range.range_end));
+    }
+    text
+}
+
+pub fn render_json(lineages: &[SeedLineage]) -> String {
+    let seeds: Vec<String> = lineages.iter().map(|l| format!(
+        "{{\"seed\":[{},{}],\"children\":[{}]}}",
+        l.seed.range_start,
+        l.seed.range_end,
+        l.children.iter().map(|c| c.to_json()).collect::<Vec<_>>().join(","),
+    )).collect();
+    let minimum = match overall_minimum(lineages) {
+        Some((location, seed, seed_range)) => format!(
+            "{{\"location\":{location},\"seed\":{seed},\"seed_range\":[{},{}]}}",
+            seed_range.range_start,
+            seed_range.range_end,
+        ),
+        None => "null".to_string(),
+    };
+    format!("{{\"seeds\":[{}],\"minimum\":{minimum}}}", seeds.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::categories::CategoryGraph;
+    use crate::parse_remainder;
+
+    const EXAMPLE: &str = "seed-to-soil map:
+50 98 2
+52 50 48
+
+soil-to-fertilizer map:
+0 15 37
+37 52 2
+39 0 15
+
+fertilizer-to-water map:
+49 53 8
+0 11 42
+42 0 7
+57 7 4
+
+water-to-light map:
+88 18 7
+18 25 70
+
+light-to-temperature map:
+45 77 23
+81 45 19
+68 64 13
+
+temperature-to-humidity map:
+0 69 1
+1 0 69
+
+humidity-to-location map:
+60 56 37
+56 93 4";
+
+    #[test]
+    fn test_trace_example() {
+        let (sections, errors) = parse_remainder(&mut EXAMPLE.lines().map(|l| Ok(l.to_string())), 3);
+        assert!(errors.is_empty());
+        let graph = CategoryGraph::new(sections, "seed").ok().unwrap();
+        let path = graph.path("seed", "location").unwrap();
+        let seeds = [BasicRange { range_start: 79, range_end: 93 }, BasicRange { range_start: 55, range_end: 68 }];
+        let lineages = trace(&path, &seeds);
+        assert!(render_text(&lineages).ends_with("Minimum 46 from seed 82 (seed range 79..93)\n"));
+        assert_eq!(lineages[0].minimum(), (46, 82));
+
+        // Seeds 79..93 become soil 81..95, which no soil-to-fertilizer mapping handles
+        let node = &lineages[0].children[0].children[0];
+        assert_eq!((node.section.as_str(), node.line), ("soil-to-fertilizer", None));
+        assert_eq!((node.input.range_start, node.input.range_end), (81, 95));
+        assert_eq!(node.minimum(), (46, 84));
+    }
+}
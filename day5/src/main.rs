@@ -3,19 +3,32 @@ use std::{env, fs::File, path::Path};
 use std::io::{self, BufRead};
 use regex::Regex;
 use lazy_static::lazy_static;
+use categories::{CategoryGraph, Section};
+use range_set::RangeSet;
+
+mod categories;
+mod inverse;
+mod lineage;
+mod piecewise;
+mod range_set;
+mod validation;
 
 
 lazy_static! {
     static ref SEEDS_RE: Regex = Regex::new(r"^seeds: ([\d ]+)$").unwrap();
+    static ref HEADER_RE: Regex = Regex::new(r"^(\w+)-to-(\w+) map:$").unwrap();
 }
 
 pub trait Range {
     fn in_range(&self, number: u64) -> bool;
     fn offset(&self, number: u64) -> u64;
     fn intersect(&self, other: &Self) -> Option<Self> where Self:Sized;
+    fn is_empty(&self) -> bool;
+    /// Whether the two ranges overlap or are directly next to each other.
+    fn touches(&self, other: &Self) -> bool;
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 struct BasicRange {
     range_start: u64,
     range_end: u64
@@ -40,8 +53,17 @@ impl Range for BasicRange {
             None
         }
     }
+
+    fn is_empty(&self) -> bool {
+        self.range_end <= self.range_start
+    }
+
+    fn touches(&self, other: &Self) -> bool {
+        self.range_start <= other.range_end && other.range_start <= self.range_end
+    }
 }
 
+#[derive(Clone)]
 struct BasicMapping {
     origin: BasicRange,
     destination: BasicRange,
@@ -49,10 +71,10 @@ struct BasicMapping {
 
 trait MappingCollection {
     fn get_mapped_from_origin(&self, number: u64) -> u64;
-    fn map_ranges_from_origin(&self, range: Vec<BasicRange>) -> Vec<BasicRange>;
+    fn map_ranges_from_origin(&self, ranges: RangeSet) -> RangeSet;
 }
 
-// #[derive(Clone)]
+#[derive(Clone)]
 struct BasicMappingCollection {
     ranges: Vec<BasicMapping>,
 }
@@ -66,35 +88,22 @@ impl MappingCollection for BasicMappingCollection {
         }
         number
     }
-    fn map_ranges_from_origin(&self, ranges: Vec<BasicRange>) -> Vec<BasicRange> {
-        let mut new_ranges_to_process = ranges.clone();
-        let mut final_ranges: Vec<BasicRange> = Vec::new();
-        loop {
-            let next_processing: Vec<_> = new_ranges_to_process.drain(..).collect();
-            'outer: for range in next_processing.iter() {
-                for mapping in self.ranges.iter() {
-                    if let Some(intersection) = range.intersect(&mapping.origin) {
-                        let start_offset = intersection.range_start - mapping.origin.range_start;
-                        let end_offset = mapping.origin.range_end - intersection.range_end;
-                        final_ranges.push(BasicRange { range_start: mapping.destination.range_start + start_offset, range_end: mapping.destination.range_end - end_offset});
-                        if intersection.range_start > range.range_start {
-                            new_ranges_to_process.push(BasicRange { range_start: range.range_start, range_end: intersection.range_start });
-                        }
-                        if intersection.range_end < range.range_end {
-                            new_ranges_to_process.push(BasicRange { range_start: intersection.range_end, range_end: range.range_end });
-                        }
-                        continue 'outer;
-                    } else {
-                        continue;
-                    }
-                }
-                final_ranges.push(range.clone());
-            }
-            if new_ranges_to_process.is_empty() {
+    fn map_ranges_from_origin(&self, ranges: RangeSet) -> RangeSet {
+        let mut remaining = ranges;
+        let mut mapped: Vec<BasicRange> = Vec::new();
+        for mapping in self.ranges.iter() {
+            if remaining.is_empty() {
                 break;
             }
+            let origin = RangeSet::from(mapping.origin.clone());
+            for intersection in remaining.intersection(&origin).iter() {
+                let start_offset = mapping.origin.offset(intersection.range_start);
+                let end_offset = mapping.origin.offset(intersection.range_end);
+                mapped.push(BasicRange { range_start: mapping.destination.range_start + start_offset, range_end: mapping.destination.range_start + end_offset });
+            }
+            remaining = remaining.difference(&origin);
         }
-        final_ranges
+        RangeSet::from_ranges(mapped).union(&remaining)
     }
 }
 
@@ -104,57 +113,167 @@ where P: AsRef<Path>, {
     Ok(io::BufReader::new(file).lines())
 }
 
-// fn parse_seeds(line: &str) -> Vec<u64> {
-//     let seed_numbers: Vec<u64> = SEEDS_RE.captures(line).unwrap().get(1).unwrap().as_str().split(" ").map(|n| n.parse().unwrap()).collect();
-//     seed_numbers
-// }
+fn parse_seeds(line: &str) -> Vec<u64> {
+    let seed_numbers: Vec<u64> = SEEDS_RE.captures(line).unwrap().get(1).unwrap().as_str().split(' ').map(|n| n.parse().unwrap()).collect();
+    seed_numbers
+}
 
-fn parse_seeds2(line: &str) -> Vec<BasicRange> {
-    let seed_numbers: Vec<u64> = SEEDS_RE.captures(line).unwrap().get(1).unwrap().as_str().split(" ").map(|n| n.parse().unwrap()).collect();
-    seed_numbers.chunks(2).map(|c| BasicRange {range_start: c[0], range_end: c[0] + c[1]}).collect()
+fn parse_seeds2(line: &str) -> Result<Vec<BasicRange>, String> {
+    parse_seeds(line).chunks(2).map(|c| match c[0].checked_add(c[1]) {
+        Some(range_end) => Ok(BasicRange {range_start: c[0], range_end}),
+        None => Err(format!("Line 1: seed range {} of length {} overflows u64", c[0], c[1])),
+    }).collect()
 }
 
 
-fn parse_remainder(lines: &mut io::Lines<io::BufReader<File>>) -> Vec<BasicMappingCollection>{
-    let mut mapping_collection: Vec<BasicMappingCollection> = Vec::new();
-    let mut current_mappings: Vec<BasicMapping> = Vec::new();
+/// Parses the mapping sections, `first_line_number` being the line number of the first
+/// line left in `lines`. Lines that cannot be parsed are reported and skipped.
+fn parse_remainder<I>(lines: &mut I, first_line_number: usize) -> (Vec<Section>, Vec<String>)
+where I: Iterator<Item = io::Result<String>>, {
+    let mut sections: Vec<Section> = Vec::new();
+    let mut errors: Vec<String> = Vec::new();
 
-    for line in lines {
+    for (i, line) in lines.enumerate() {
+        let line_number = first_line_number + i;
         let current_line = line.unwrap();
-        if current_line.ends_with("map:") {
+        if let Some(capture) = HEADER_RE.captures(&current_line) {
+            sections.push(Section {
+                from: capture.get(1).unwrap().as_str().to_string(),
+                to: capture.get(2).unwrap().as_str().to_string(),
+                mappings: BasicMappingCollection { ranges: Vec::new() },
+                lines: Vec::new(),
+            });
             continue;
         }
         if current_line.is_empty() {
-            mapping_collection.push(BasicMappingCollection {ranges: current_mappings.drain(..).collect()});
             continue;
         }
 
-        let numbers: Vec<u64> = current_line.split(" ").map(|s| s.parse().unwrap()).collect();
-        current_mappings.push( BasicMapping {
-            origin: BasicRange { range_start: numbers[1], range_end: numbers[1] + numbers[2] },
-            destination: BasicRange { range_start: numbers[0], range_end: numbers[0] + numbers[2] },
-        })
+        let numbers: Vec<u64> = match current_line.split(' ').map(|s| s.parse()).collect::<Result<Vec<u64>, _>>() {
+            Ok(numbers) if numbers.len() == 3 => numbers,
+            _ => {
+                errors.push(format!("Line {line_number}: expected three numbers, found {current_line:?}"));
+                continue;
+            }
+        };
+        let Some(section) = sections.last_mut() else {
+            errors.push(format!("Line {line_number}: mapping outside of any section"));
+            continue;
+        };
+        let (Some(origin_end), Some(destination_end)) = (numbers[1].checked_add(numbers[2]), numbers[0].checked_add(numbers[2])) else {
+            errors.push(format!("Line {line_number}: range of length {} overflows u64", numbers[2]));
+            continue;
+        };
+        section.mappings.ranges.push( BasicMapping {
+            origin: BasicRange { range_start: numbers[1], range_end: origin_end },
+            destination: BasicRange { range_start: numbers[0], range_end: destination_end },
+        });
+        section.lines.push(line_number);
     }
 
-    if !current_mappings.is_empty() {
-        mapping_collection.push(BasicMappingCollection {ranges: current_mappings.drain(..).collect()});
-    }
+    (sections, errors)
+}
 
-    mapping_collection
+fn exit_with_errors(errors: &[String]) -> ! {
+    errors.iter().for_each(|e| eprintln!("{e}"));
+    std::process::exit(1);
 }
 
 
+/// Parses a single number `N` or a half-open range `START..END`.
+fn parse_range_spec(spec: &str) -> Result<BasicRange, String> {
+    fn parse_number(number: &str, spec: &str) -> Result<u64, String> {
+        number.parse().map_err(|_| format!("Invalid range {spec:?}: {number:?} is not a number"))
+    }
+    match spec.split_once("..") {
+        Some((start, end)) => {
+            let (range_start, range_end) = (parse_number(start, spec)?, parse_number(end, spec)?);
+            if range_start >= range_end {
+                return Err(format!("Invalid range {spec:?}: {range_start}..{range_end} is empty"));
+            }
+            Ok(BasicRange { range_start, range_end })
+        }
+        None => {
+            let location = parse_number(spec, spec)?;
+            let range_end = location.checked_add(1).ok_or_else(|| format!("Invalid range {spec:?}: {location} overflows u64"))?;
+            Ok(BasicRange { range_start: location, range_end })
+        }
+    }
+}
+
+fn format_ranges(ranges: &RangeSet) -> String {
+    ranges.iter().map(|r| format!("{}..{}", r.range_start, r.range_end)).collect::<Vec<_>>().join(", ")
+}
+
 fn main() {
     let args: Vec<String> = env::args().collect();
     let filename = &args[1];
     let mut file_iterator = read_lines(filename).unwrap();
-    // let seeds = parse_seeds(&file_iterator.next().unwrap().unwrap());
-    let seeds = parse_seeds2(&file_iterator.next().unwrap().unwrap());
+    let seed_line = file_iterator.next().unwrap().unwrap();
     file_iterator.next();
 
-    let all_mappings: Vec<BasicMappingCollection> = parse_remainder(&mut file_iterator);
+    let (sections, mut errors) = parse_remainder(&mut file_iterator, 3);
+    let seed_ranges = parse_seeds2(&seed_line).unwrap_or_else(|e| {
+        errors.insert(0, e);
+        Vec::new()
+    });
+    if args.get(2).map(|s| s.as_str()) == Some("--validate") {
+        errors.extend(validation::validate_sections(&sections));
+        match CategoryGraph::new(sections, "seed") {
+            Ok(graph) => errors.extend(graph.chain("seed", "location").err()),
+            Err(graph_errors) => errors.extend(graph_errors),
+        }
+        if errors.is_empty() {
+            println!("No issues found");
+            return;
+        }
+        exit_with_errors(&errors);
+    }
+    if !errors.is_empty() {
+        exit_with_errors(&errors);
+    }
 
-    // let minimum_last = seeds.iter().map(|s| all_mappings.iter().fold(*s, |o, mapping| mapping.get_mapped_from_origin(o))).min().unwrap();
-    let minimum_last = all_mappings.iter().fold(seeds, |range, mapping| mapping.map_ranges_from_origin(range)).iter().map(|range| range.range_start).min().unwrap();
-    println!("Minimum: {minimum_last}");
+    let graph = CategoryGraph::new(sections, "seed").unwrap_or_else(|errors| exit_with_errors(&errors));
+    let all_mappings: Vec<BasicMappingCollection> = graph.chain("seed", "location").unwrap_or_else(|e| exit_with_errors(&[e]));
+
+    let seed_set = RangeSet::from_ranges(seed_ranges.clone());
+    match args.get(2).map(|s| s.as_str()) {
+        Some("--composed") => {
+            let composed = piecewise::PiecewiseMap::from_chain(&all_mappings);
+            print!("{composed}");
+            let minimum_seeds = parse_seeds(&seed_line).iter().map(|s| composed.get_mapped_from_origin(*s)).min().unwrap();
+            println!("Minimum over seeds (composed): {minimum_seeds}");
+            let minimum_last = composed.map_ranges_from_origin(seed_set).min().unwrap();
+            println!("Minimum (composed): {minimum_last}");
+        }
+        Some("--invert") => {
+            let locations = parse_range_spec(&args[3]).unwrap_or_else(|e| exit_with_errors(&[e]));
+            let seeds = inverse::seeds_for_locations(&all_mappings, RangeSet::from(locations));
+            println!("Seed ranges: {}", format_ranges(&seeds));
+            let almanac_seeds = seed_set.intersection(&seeds);
+            println!("Of which almanac seeds: {}", format_ranges(&almanac_seeds));
+        }
+        Some("--map") => {
+            let chain = graph.chain(&args[3], &args[4]).unwrap_or_else(|e| exit_with_errors(&[e]));
+            let ranges = RangeSet::from(parse_range_spec(&args[5]).unwrap_or_else(|e| exit_with_errors(&[e])));
+            let mapped = chain.iter().fold(ranges, |ranges, mapping| mapping.map_ranges_from_origin(ranges));
+            println!("{} {} -> {} {}", args[3], args[5], args[4], format_ranges(&mapped));
+        }
+        Some(option @ ("--trace" | "--trace-json")) => {
+            let path = graph.path("seed", "location").unwrap();
+            let lineages = lineage::trace(&path, &seed_ranges);
+            if option == "--trace" {
+                print!("{}", lineage::render_text(&lineages));
+            } else {
+                println!("{}", lineage::render_json(&lineages));
+            }
+        }
+        Some(option) => panic!("Unknown option: {option}"),
+        None => {
+            let minimum_seeds = parse_seeds(&seed_line).iter().map(|s| all_mappings.iter().fold(*s, |o, mapping| mapping.get_mapped_from_origin(o))).min().unwrap();
+            println!("Minimum over seeds: {minimum_seeds}");
+            let minimum_last = all_mappings.iter().fold(seed_set, |ranges, mapping| mapping.map_ranges_from_origin(ranges)).min().unwrap();
+            println!("Minimum: {minimum_last}");
+        }
+    }
 }
@@ -0,0 +1,149 @@
+use std::fmt;
+
+use crate::range_set::RangeSet;
+use crate::{BasicMapping, BasicMappingCollection, BasicRange, MappingCollection, Range};
+
+
+/// Shifts `range`, a part of `mapping.origin`, to where the mapping sends it.
+fn map_subrange(mapping: &BasicMapping, range: &BasicRange) -> BasicRange {
+    BasicRange {
+        range_start: mapping.destination.range_start + mapping.origin.offset(range.range_start),
+        range_end: mapping.destination.range_start + mapping.origin.offset(range.range_end),
+    }
+}
+
+/// Sorts mappings by origin and merges neighbours that apply the same shift.
+fn merge_pieces(mut pieces: Vec<BasicMapping>) -> Vec<BasicMapping> {
+    pieces.sort_by_key(|p| p.origin.range_start);
+    let mut merged: Vec<BasicMapping> = Vec::with_capacity(pieces.len());
+    for piece in pieces {
+        match merged.last_mut() {
+            Some(last) if last.origin.range_end == piece.origin.range_start && last.destination.range_end == piece.destination.range_start => {
+                last.origin.range_end = piece.origin.range_end;
+                last.destination.range_end = piece.destination.range_end;
+            }
+            _ => merged.push(piece),
+        }
+    }
+    merged
+}
+
+impl BasicMappingCollection {
+    /// Disjoint mappings covering every number, in origin order, that behave like this
+    /// collection: earlier mappings win on overlap and gaps map to themselves.
+    pub fn pieces(&self) -> Vec<BasicMapping> {
+        let mut covered = RangeSet::default();
+        let mut pieces = Vec::new();
+        for mapping in self.ranges.iter() {
+            let origin = RangeSet::from(mapping.origin.clone());
+            for range in origin.difference(&covered).iter() {
+                pieces.push(BasicMapping { origin: range.clone(), destination: map_subrange(mapping, range) });
+            }
+            covered = covered.union(&origin);
+        }
+        for gap in covered.complement().iter() {
+            pieces.push(BasicMapping { origin: gap.clone(), destination: gap.clone() });
+        }
+        merge_pieces(pieces)
+    }
+
+    /// Single collection equivalent to applying `self` then `other`.
+    pub fn compose(&self, other: &BasicMappingCollection) -> BasicMappingCollection {
+        let other_pieces = other.pieces();
+        let mut pieces = Vec::new();
+        for piece in self.pieces() {
+            let image = &piece.destination;
+            let first = other_pieces.partition_point(|p| p.origin.range_end <= image.range_start);
+            for next in other_pieces[first..].iter().take_while(|p| p.origin.range_start < image.range_end) {
+                let common = image.intersect(&next.origin).unwrap();
+                let origin = BasicRange {
+                    range_start: piece.origin.range_start + image.offset(common.range_start),
+                    range_end: piece.origin.range_start + image.offset(common.range_end),
+                };
+                pieces.push(BasicMapping { origin, destination: map_subrange(next, &common) });
+            }
+        }
+        BasicMappingCollection { ranges: merge_pieces(pieces) }
+    }
+}
+
+/// Whole chain of collections folded into one sorted, gap-free list of pieces, so that
+/// a lookup is a single binary search.
+pub struct PiecewiseMap {
+    pieces: Vec<BasicMapping>,
+}
+
+impl PiecewiseMap {
+    pub fn from_chain(chain: &[BasicMappingCollection]) -> Self {
+        let identity = BasicMappingCollection { ranges: Vec::new() };
+        let composed = chain.iter().fold(identity, |composed, next| composed.compose(next));
+        PiecewiseMap { pieces: composed.pieces() }
+    }
+}
+
+impl MappingCollection for PiecewiseMap {
+    fn get_mapped_from_origin(&self, number: u64) -> u64 {
+        let index = self.pieces.partition_point(|p| p.origin.range_end <= number);
+        match self.pieces.get(index) {
+            Some(piece) if piece.origin.in_range(number) => piece.destination.range_start + piece.origin.offset(number),
+            _ => number,
+        }
+    }
+
+    fn map_ranges_from_origin(&self, ranges: RangeSet) -> RangeSet {
+        let mut mapped = Vec::new();
+        for range in ranges.iter() {
+            let first = self.pieces.partition_point(|p| p.origin.range_end <= range.range_start);
+            for piece in self.pieces[first..].iter().take_while(|p| p.origin.range_start < range.range_end) {
+                mapped.push(map_subrange(piece, &range.intersect(&piece.origin).unwrap()));
+            }
+        }
+        RangeSet::from_ranges(mapped)
+    }
+}
+
+impl fmt::Display for PiecewiseMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for piece in self.pieces.iter() {
+            let shift = piece.destination.range_start as i128 - piece.origin.range_start as i128;
+            writeln!(
+                f,
+                "[{}, {}) -> [{}, {}) ({:+})",
+                piece.origin.range_start,
+                piece.origin.range_end,
+                piece.destination.range_start,
+                piece.destination.range_end,
+                shift,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collection(mappings: &[(u64, u64, u64)]) -> BasicMappingCollection {
+        BasicMappingCollection {
+            ranges: mappings.iter().map(|(destination, origin, length)| BasicMapping {
+                origin: BasicRange { range_start: *origin, range_end: origin + length },
+                destination: BasicRange { range_start: *destination, range_end: destination + length },
+            }).collect(),
+        }
+    }
+
+    #[test]
+    fn test_compose_matches_sequential_lookup() {
+        let chain = vec![
+            collection(&[(50, 98, 2), (52, 50, 48)]),
+            collection(&[(0, 15, 37), (37, 52, 2), (39, 0, 15)]),
+            collection(&[(49, 53, 8), (0, 11, 42), (42, 0, 7), (57, 7, 4)]),
+        ];
+        let composed = PiecewiseMap::from_chain(&chain);
+        for seed in 0..200 {
+            let expected = chain.iter().fold(seed, |n, c| c.get_mapped_from_origin(n));
+            assert_eq!(composed.get_mapped_from_origin(seed), expected, "seed {seed}");
+        }
+    }
+}
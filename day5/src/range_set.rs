@@ -0,0 +1,110 @@
+use std::cmp::max;
+
+use crate::{BasicRange, Range};
+
+
+/// Set of `u64` built from half-open ranges, kept normalized: sorted, non-empty, and
+/// with no two ranges overlapping or touching.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RangeSet {
+    ranges: Vec<BasicRange>,
+}
+
+impl RangeSet {
+    pub fn from_ranges(mut ranges: Vec<BasicRange>) -> Self {
+        ranges.retain(|r| !r.is_empty());
+        ranges.sort_by_key(|r| r.range_start);
+        let mut normalized: Vec<BasicRange> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match normalized.last_mut() {
+                Some(last) if last.touches(&range) => last.range_end = max(last.range_end, range.range_end),
+                _ => normalized.push(range),
+            }
+        }
+        RangeSet { ranges: normalized }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &BasicRange> {
+        self.ranges.iter()
+    }
+
+    pub fn min(&self) -> Option<u64> {
+        self.ranges.first().map(|r| r.range_start)
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        Self::from_ranges(self.ranges.iter().chain(other.ranges.iter()).cloned().collect())
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let mut result = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < self.ranges.len() && j < other.ranges.len() {
+            if let Some(common) = self.ranges[i].intersect(&other.ranges[j]) {
+                result.push(common);
+            }
+            if self.ranges[i].range_end < other.ranges[j].range_end {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        RangeSet { ranges: result }
+    }
+
+    /// Everything in `0..u64::MAX` that is not in the set.
+    pub fn complement(&self) -> Self {
+        let mut result = Vec::new();
+        let mut start = 0;
+        for range in self.ranges.iter() {
+            if range.range_start > start {
+                result.push(BasicRange { range_start: start, range_end: range.range_start });
+            }
+            start = range.range_end;
+        }
+        if start < u64::MAX {
+            result.push(BasicRange { range_start: start, range_end: u64::MAX });
+        }
+        RangeSet { ranges: result }
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        self.intersection(&other.complement())
+    }
+}
+
+impl From<BasicRange> for RangeSet {
+    fn from(range: BasicRange) -> Self {
+        Self::from_ranges(vec![range])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(ranges: &[(u64, u64)]) -> RangeSet {
+        RangeSet::from_ranges(ranges.iter().map(|(s, e)| BasicRange { range_start: *s, range_end: *e }).collect())
+    }
+
+    #[test]
+    fn test_normalization() {
+        assert_eq!(set(&[(5, 10), (0, 3), (3, 4), (8, 12), (20, 20)]), set(&[(0, 4), (5, 12)]));
+        assert!(set(&[(7, 7)]).is_empty());
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let a = set(&[(0, 10), (20, 30)]);
+        let b = set(&[(5, 25)]);
+        assert_eq!(a.union(&b), set(&[(0, 30)]));
+        assert_eq!(a.intersection(&b), set(&[(5, 10), (20, 25)]));
+        assert_eq!(a.difference(&b), set(&[(0, 5), (25, 30)]));
+        assert_eq!(a.complement(), set(&[(10, 20), (30, u64::MAX)]));
+        assert_eq!(a.complement().complement(), a);
+    }
+}
@@ -0,0 +1,67 @@
+use crate::categories::Section;
+
+
+/// Reports zero-length mappings and mappings whose origins overlap within a section,
+/// in which case only the first one listed is ever applied.
+pub fn validate_sections(sections: &[Section]) -> Vec<String> {
+    let mut issues = Vec::new();
+    for section in sections.iter() {
+        let name = format!("{}-to-{}", section.from, section.to);
+        let mut order: Vec<usize> = (0..section.mappings.ranges.len()).collect();
+        order.sort_by_key(|i| section.mappings.ranges[*i].origin.range_start);
+
+        // Mapping reaching furthest among those already seen, the only one a later
+        // mapping can overlap without also overlapping its immediate predecessor
+        let mut furthest: Option<usize> = None;
+        for i in order {
+            let mapping = &section.mappings.ranges[i];
+            if mapping.origin.range_start == mapping.origin.range_end {
+                issues.push(format!("Line {}: zero-length range in {name}", section.lines[i]));
+                continue;
+            }
+            if let Some(j) = furthest {
+                let previous = &section.mappings.ranges[j];
+                if mapping.origin.range_start < previous.origin.range_end {
+                    issues.push(format!(
+                        "Lines {} and {}: overlapping origins {}..{} and {}..{} in {name}",
+                        section.lines[j].min(section.lines[i]),
+                        section.lines[j].max(section.lines[i]),
+                        previous.origin.range_start,
+                        previous.origin.range_end,
+                        mapping.origin.range_start,
+                        mapping.origin.range_end,
+                    ));
+                }
+                if mapping.origin.range_end <= previous.origin.range_end {
+                    continue;
+                }
+            }
+            furthest = Some(i);
+        }
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_remainder;
+
+    #[test]
+    fn test_validate_sections() {
+        let almanac = "seed-to-soil map:
+100 0 50
+200 10 5
+300 40 20
+400 70 0
+1 18446744073709551615 2";
+        let (sections, errors) = parse_remainder(&mut almanac.lines().map(|l| Ok(l.to_string())), 3);
+        assert_eq!(errors, vec!["Line 8: range of length 2 overflows u64"]);
+        // 10..15 lies within 0..50, and 40..60 overlaps 0..50 but not 10..15
+        assert_eq!(validate_sections(&sections), vec![
+            "Lines 4 and 5: overlapping origins 0..50 and 10..15 in seed-to-soil",
+            "Lines 4 and 6: overlapping origins 0..50 and 40..60 in seed-to-soil",
+            "Line 7: zero-length range in seed-to-soil",
+        ]);
+    }
+}
@@ -1,6 +1,10 @@
-// use std::iter::zip;
+use std::iter::zip;
 use std::{env, fs::File, path::Path};
 use std::io::{self, BufRead};
+use models::BoatModel;
+
+mod models;
+mod report;
 
 
 struct Race {
@@ -14,52 +18,146 @@ where P: AsRef<Path>, {
     Ok(io::BufReader::new(file).lines())
 }
 
-// fn parse_inputs(lines: &mut io::Lines<io::BufReader<File>>) -> Vec<Race> {
-//     let time_line = lines.next().unwrap().unwrap();
-//     assert!(time_line.starts_with("Time:"));
-//     let time_numbers: Vec<u64> = time_line.split(" ").skip(1).filter_map(|s| s.parse().ok()).collect();
+/// Race sheet as written, one entry per column.
+struct RaceSheet {
+    times: Vec<String>,
+    distances: Vec<String>,
+}
+
+impl RaceSheet {
+    /// One race per column.
+    fn races(&self) -> Result<Vec<Race>, String> {
+        fn parse(number: &str) -> Result<u64, String> {
+            number.parse().map_err(|_| format!("Invalid number: {number}"))
+        }
+        zip(self.times.iter(), self.distances.iter()).map(|(t, d)| Ok(Race { time: parse(t)?, distance: parse(d)? })).collect()
+    }
 
-//     let distance_line = lines.next().unwrap().unwrap();
-//     assert!(distance_line.starts_with("Distance:"));
-//     let distance_numbers: Vec<u64> = distance_line.split(" ").skip(1).filter_map(|s| s.parse().ok()).collect();
-//     zip(time_numbers, distance_numbers).map(|(t, d)| Race {time: t, distance: d}).collect()
-// }
+    /// Single race read by ignoring the spaces between columns.
+    fn kerned(&self) -> Result<Race, String> {
+        fn parse(numbers: &[String]) -> Result<u64, String> {
+            let joined = numbers.join("");
+            joined.parse().map_err(|_| format!("Invalid kerned number: {joined}"))
+        }
+        Ok(Race { time: parse(&self.times)?, distance: parse(&self.distances)? })
+    }
+}
 
-fn parse_inputs2(lines: &mut io::Lines<io::BufReader<File>>) -> Race {
-    let time_line = lines.next().unwrap().unwrap();
-    assert!(time_line.starts_with("Time:"));
-    let time_number: u64 = time_line.split(" ").skip(1).filter(|s| !s.is_empty()).map(|s| s.to_string()).collect::<Vec<String>>().join("").parse().unwrap();
+fn parse_sheet(lines: &mut io::Lines<io::BufReader<File>>) -> Result<RaceSheet, String> {
+    fn parse_line(line: Option<io::Result<String>>, header: &str) -> Result<Vec<String>, String> {
+        let line = line.ok_or(format!("Missing {header} line"))?.map_err(|e| e.to_string())?;
+        let numbers = line.strip_prefix(header).ok_or(format!("Expected a line starting with {header}, found {line:?}"))?;
+        Ok(numbers.split(' ').filter(|s| !s.is_empty()).map(|s| s.to_string()).collect())
+    }
+    let times = parse_line(lines.next(), "Time:")?;
+    let distances = parse_line(lines.next(), "Distance:")?;
+    if times.len() != distances.len() {
+        return Err(format!("Found {} times but {} distances", times.len(), distances.len()));
+    }
+    Ok(RaceSheet { times, distances })
+}
+
+fn beats_record(race: &Race, hold: u64) -> bool {
+    hold as u128 * (race.time - hold) as u128 > race.distance as u128
+}
 
-    let distance_line = lines.next().unwrap().unwrap();
-    assert!(distance_line.starts_with("Distance:"));
-    let distance_number: u64 = distance_line.split(" ").skip(1).filter(|s| !s.is_empty()).map(|s| s.to_string()).collect::<Vec<String>>().join("").parse().unwrap();
+/// Range of winning hold times, if any. The winning holds are the integers strictly
+/// between the roots of `hold * (time - hold) = distance`; the roots are found with an
+/// integer square root in `u128`, then nudged to the exact boundary.
+fn solve_poly2(race: &Race) -> Option<(u64, u64)> {
+    let time = race.time as u128;
+    let det = (time * time).checked_sub(4 * (race.distance as u128 + 1))?;
 
-    Race { time: time_number, distance: distance_number }
+    // Near the lower root, then corrected for the flooring of the square root
+    let mut low = ((time - det.isqrt()) / 2) as u64;
+    while low > 0 && beats_record(race, low - 1) {
+        low -= 1;
+    }
+    while low <= race.time / 2 && !beats_record(race, low) {
+        low += 1;
+    }
+    if low > race.time / 2 {
+        return None;
+    }
+    // Distance is symmetric around time / 2
+    Some((low, race.time - low))
 }
 
-fn solve_poly2(race: &Race) -> (u64, u64) {
-    // It's just a 2nd degree polynomial
-    let time = race.time as f64;
-    let dist = (race.distance + 1) as f64;  // We need to beat the record
 
-    let det = time.powi(2) - 4.0 * dist;
-    (
-        ((time - det.sqrt()) / 2.).ceil() as u64,
-        ((time + det.sqrt()) / 2.).floor() as u64,
-    )
+fn count_winning(model: &dyn BoatModel, race: &Race) -> u64 {
+    model.winning_holds(race).map_or(0, |(l, r)| r - l + 1)
 }
 
+fn run(args: &[String]) -> Result<(), String> {
+    let mut file_iterator = read_lines(&args[1]).map_err(|e| e.to_string())?;
+    let sheet = parse_sheet(&mut file_iterator)?;
+    let mut model: Box<dyn BoatModel> = Box::new(models::Accelerating { speed_per_ms: 1 });
+    let mut columns = false;
+    let mut output: Option<&str> = None;
+    let mut options = args[2..].iter();
+    while let Some(option) = options.next() {
+        match option.as_str() {
+            "--columns" => columns = true,
+            "--kerned" => columns = false,
+            "--report" | "--report-json" => output = Some(option),
+            "--model" => model = models::parse_model(options.next().ok_or("Missing boat model")?)?,
+            _ => return Err(format!("Unknown option: {option}")),
+        }
+    }
+
+    let races = if columns { sheet.races()? } else { vec![sheet.kerned()?] };
+    if let Some(output) = output {
+        let reports: Vec<report::RaceReport> = races.iter().map(|race| report::RaceReport::new(model.as_ref(), race)).collect();
+        match output {
+            "--report" => print!("{}", report::render_table(&reports)),
+            _ => println!("{}", report::render_json(&reports)),
+        }
+    } else if columns {
+        let prod_possibilities: u64 = races.iter().map(|race| count_winning(model.as_ref(), race)).product();
+        println!("Product of possibilities: {prod_possibilities}");
+    } else {
+        let possibilities = count_winning(model.as_ref(), &races[0]);
+        println!("Possibilities: {possibilities}");
+    }
+    Ok(())
+}
 
 fn main() {
     let args: Vec<String> = env::args().collect();
-    let filename = &args[1];
-    let mut file_iterator = read_lines(filename).unwrap();
-    // let races = parse_inputs(&mut file_iterator);
+    if let Err(e) = run(&args) {
+        eprintln!("{e}");
+        std::process::exit(1);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn brute_force(race: &Race) -> Option<(u64, u64)> {
+        let winning: Vec<u64> = (0..=race.time).filter(|hold| hold * (race.time - hold) > race.distance).collect();
+        Some((*winning.first()?, *winning.last()?))
+    }
 
-    // let prod_possibilities: u64 = races.iter().map(solve_poly2).map(|(l, r)| r - l + 1).product();
-    // println!("Product of possibilities: {prod_possibilities}");
+    #[test]
+    fn test_solve_poly2_against_brute_force() {
+        for time in 0..60 {
+            for distance in 0..(time * time / 4 + 3) {
+                let race = Race { time, distance };
+                assert_eq!(solve_poly2(&race), brute_force(&race), "time {time}, distance {distance}");
+            }
+        }
+    }
 
-    let (l, r) = solve_poly2(&parse_inputs2(&mut file_iterator));
-    let possibilities = r - l + 1;
-    println!("Possibilities: {possibilities}");
+    #[test]
+    fn test_solve_poly2_large() {
+        // Perfect square discriminant: the roots 1 and time - 1 exactly tie the record
+        let time = u64::MAX;
+        let race = Race { time, distance: time - 1 };
+        assert_eq!(solve_poly2(&race), Some((2, time - 2)));
+        // Only the exact middle beats a record one short of the maximum distance
+        let race = Race { time: 1 << 33, distance: u64::MAX };
+        assert_eq!(solve_poly2(&race), Some((1 << 32, 1 << 32)));
+    }
 }
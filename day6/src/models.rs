@@ -0,0 +1,204 @@
+use crate::{solve_poly2, Race};
+
+
+/// How the boat turns time spent holding the button into distance.
+pub trait BoatModel {
+    /// Distance covered in a `time` ms race when holding the button for `hold` ms,
+    /// saturating at `u128::MAX`.
+    fn distance(&self, hold: u64, time: u64) -> u128;
+
+    /// Range of hold times beating the record. The default binary searches the distance
+    /// curve, which must not decrease up to its peak and strictly decrease after it.
+    fn winning_holds(&self, race: &Race) -> Option<(u64, u64)> {
+        monotone_search(self, race)
+    }
+
+    /// Shortest hold reaching the longest distance, found with the same assumptions on
+    /// the distance curve as `winning_holds`.
+    fn optimal_hold(&self, time: u64) -> u64 {
+        peak_search(self, time)
+    }
+}
+
+/// Smallest `x` in `low..=high` for which `predicate` holds, given that it is false up
+/// to some point and true afterwards.
+fn first_true<F>(mut low: u64, mut high: u64, predicate: F) -> Option<u64>
+where F: Fn(u64) -> bool, {
+    if low > high || !predicate(high) {
+        return None;
+    }
+    while low < high {
+        let middle = low + (high - low) / 2;
+        if predicate(middle) {
+            high = middle;
+        } else {
+            low = middle + 1;
+        }
+    }
+    Some(low)
+}
+
+fn peak_search<M>(model: &M, time: u64) -> u64
+where M: BoatModel + ?Sized, {
+    let distance = |hold: u64| model.distance(hold, time);
+    // First hold after which the distance strictly falls, which is where it peaks. If
+    // it never does, the boat never moves and not holding at all is as good as any.
+    let peak = first_true(0, time.saturating_sub(1), |hold| distance(hold + 1) < distance(hold)).unwrap_or(0);
+    let best = distance(peak);
+    first_true(0, peak, |hold| distance(hold) >= best).unwrap()
+}
+
+fn monotone_search<M>(model: &M, race: &Race) -> Option<(u64, u64)>
+where M: BoatModel + ?Sized, {
+    let time = race.time;
+    let record = race.distance as u128;
+    let distance = |hold: u64| model.distance(hold, time);
+    let peak = model.optimal_hold(time);
+    if distance(peak) <= record {
+        return None;
+    }
+    let low = first_true(0, peak, |hold| distance(hold) > record).unwrap();
+    let high = first_true(peak, time, |hold| distance(hold) <= record).map_or(time, |h| h - 1);
+    Some((low, high))
+}
+
+/// The puzzle's boat: each ms of holding adds `speed_per_ms` to the speed.
+pub struct Accelerating {
+    pub speed_per_ms: u64,
+}
+
+impl BoatModel for Accelerating {
+    fn distance(&self, hold: u64, time: u64) -> u128 {
+        (self.speed_per_ms as u128 * hold as u128).saturating_mul(time.saturating_sub(hold) as u128)
+    }
+
+    /// `speed * hold * (time - hold) > distance` holds exactly when
+    /// `hold * (time - hold) > distance / speed`, rounding down.
+    fn winning_holds(&self, race: &Race) -> Option<(u64, u64)> {
+        if self.speed_per_ms == 0 {
+            return None;
+        }
+        solve_poly2(&Race { time: race.time, distance: race.distance / self.speed_per_ms })
+    }
+
+    /// Half the race, unless the distance saturates and an earlier hold reaches it too.
+    fn optimal_hold(&self, time: u64) -> u64 {
+        if self.speed_per_ms == 0 {
+            return 0;
+        }
+        if self.distance(time / 2, time) == u128::MAX {
+            return peak_search(self, time);
+        }
+        time / 2
+    }
+}
+
+/// Accelerating boat whose speed cannot go over `max_speed`.
+pub struct Capped {
+    pub speed_per_ms: u64,
+    pub max_speed: u64,
+}
+
+impl BoatModel for Capped {
+    fn distance(&self, hold: u64, time: u64) -> u128 {
+        let speed = (self.speed_per_ms as u128 * hold as u128).min(self.max_speed as u128);
+        speed.saturating_mul(time.saturating_sub(hold) as u128)
+    }
+}
+
+/// Accelerating boat that only starts charging after `delay` ms of holding.
+pub struct Delayed {
+    pub speed_per_ms: u64,
+    pub delay: u64,
+}
+
+impl BoatModel for Delayed {
+    fn distance(&self, hold: u64, time: u64) -> u128 {
+        let charged = hold.saturating_sub(self.delay);
+        (self.speed_per_ms as u128 * charged as u128).saturating_mul(time.saturating_sub(hold) as u128)
+    }
+
+    /// Same as `Accelerating` on a race shortened by the delay, shifted back.
+    fn winning_holds(&self, race: &Race) -> Option<(u64, u64)> {
+        let shortened = Race { time: race.time.checked_sub(self.delay)?, distance: race.distance };
+        let (low, high) = Accelerating { speed_per_ms: self.speed_per_ms }.winning_holds(&shortened)?;
+        Some((low + self.delay, high + self.delay))
+    }
+
+    fn optimal_hold(&self, time: u64) -> u64 {
+        if time <= self.delay.saturating_add(1) || self.speed_per_ms == 0 {
+            return 0;
+        }
+        let optimal = self.delay + (time - self.delay) / 2;
+        if self.distance(optimal, time) == u128::MAX {
+            return peak_search(self, time);
+        }
+        optimal
+    }
+}
+
+/// Parses `accelerating:SPEED`, `capped:SPEED:MAX` or `delayed:SPEED:DELAY`.
+pub fn parse_model(spec: &str) -> Result<Box<dyn BoatModel>, String> {
+    let parts: Vec<&str> = spec.split(':').collect();
+    let numbers: Vec<u64> = parts[1..].iter().map(|p| p.parse().map_err(|_| format!("Invalid number in model: {p}"))).collect::<Result<_, _>>()?;
+    match (parts[0], numbers.as_slice()) {
+        ("accelerating", [speed_per_ms]) => Ok(Box::new(Accelerating { speed_per_ms: *speed_per_ms })),
+        ("capped", [speed_per_ms, max_speed]) => Ok(Box::new(Capped { speed_per_ms: *speed_per_ms, max_speed: *max_speed })),
+        ("delayed", [speed_per_ms, delay]) => Ok(Box::new(Delayed { speed_per_ms: *speed_per_ms, delay: *delay })),
+        _ => Err(format!("Unknown boat model: {spec}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn brute_force(model: &dyn BoatModel, race: &Race) -> Option<(u64, u64)> {
+        let winning: Vec<u64> = (0..=race.time).filter(|hold| model.distance(*hold, race.time) > race.distance as u128).collect();
+        Some((*winning.first()?, *winning.last()?))
+    }
+
+    #[test]
+    fn test_models_against_brute_force() {
+        let models: Vec<Box<dyn BoatModel>> = vec![
+            Box::new(Accelerating { speed_per_ms: 0 }),
+            Box::new(Accelerating { speed_per_ms: 1 }),
+            Box::new(Accelerating { speed_per_ms: 3 }),
+            Box::new(Capped { speed_per_ms: 2, max_speed: 7 }),
+            Box::new(Capped { speed_per_ms: 1, max_speed: 100 }),
+            Box::new(Delayed { speed_per_ms: 2, delay: 3 }),
+        ];
+        for model in models.iter() {
+            for time in 0..40 {
+                for distance in 0..(time * time + 3) {
+                    let race = Race { time, distance };
+                    let expected = brute_force(model.as_ref(), &race);
+                    assert_eq!(model.winning_holds(&race), expected, "time {time}, distance {distance}");
+                    assert_eq!(monotone_search(model.as_ref(), &race), expected, "time {time}, distance {distance}");
+                    let best = (0..=time).map(|hold| model.distance(hold, time)).max().unwrap();
+                    let optimal = model.optimal_hold(time);
+                    assert_eq!(model.distance(optimal, time), best, "time {time}");
+                    assert!((0..optimal).all(|hold| model.distance(hold, time) < best), "time {time}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_models_saturate_on_large_races() {
+        let time = 1 << 60;
+        let models: Vec<Box<dyn BoatModel>> = vec![
+            Box::new(Accelerating { speed_per_ms: 1 << 20 }),
+            Box::new(Delayed { speed_per_ms: 1 << 20, delay: 1 << 40 }),
+        ];
+        for model in models.iter() {
+            let optimal = model.optimal_hold(time);
+            assert_eq!(model.distance(optimal, time), u128::MAX);
+            assert!(model.distance(optimal - 1, time) < u128::MAX);
+            let race = Race { time, distance: u64::MAX };
+            let winning = model.winning_holds(&race).unwrap();
+            assert_eq!(Some(winning), monotone_search(model.as_ref(), &race));
+            assert!(winning.0 <= optimal && optimal <= winning.1);
+        }
+    }
+}
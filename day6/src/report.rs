@@ -0,0 +1,100 @@
+use crate::models::BoatModel;
+use crate::Race;
+
+
+/// How a race can best be played, and how much room there is around the best hold.
+pub struct RaceReport {
+    time: u64,
+    record: u64,
+    optimal_hold: u64,
+    max_distance: u128,
+    winning: Option<(u64, u64)>,
+}
+
+impl RaceReport {
+    pub fn new(model: &dyn BoatModel, race: &Race) -> Self {
+        let optimal_hold = model.optimal_hold(race.time);
+        RaceReport {
+            time: race.time,
+            record: race.distance,
+            optimal_hold,
+            max_distance: model.distance(optimal_hold, race.time),
+            winning: model.winning_holds(race),
+        }
+    }
+
+    /// Whether the best hold beats the record, and by how much it goes further than the
+    /// record or falls short of it.
+    fn margin(&self) -> (bool, u128) {
+        let record = self.record as u128;
+        if self.max_distance > record {
+            (true, self.max_distance - record)
+        } else {
+            (false, record - self.max_distance)
+        }
+    }
+
+    /// The margin as a signed number, negative when the record cannot be beaten.
+    fn format_margin(&self) -> String {
+        match self.margin() {
+            (false, shortfall) if shortfall > 0 => format!("-{shortfall}"),
+            (_, margin) => margin.to_string(),
+        }
+    }
+
+    fn count(&self) -> u64 {
+        self.winning.map_or(0, |(low, high)| high - low + 1)
+    }
+
+    /// Milliseconds the hold can be shortened or lengthened from the optimal one while
+    /// still winning.
+    fn slack(&self) -> Option<(u64, u64)> {
+        self.winning.map(|(low, high)| (self.optimal_hold - low, high - self.optimal_hold))
+    }
+}
+
+pub fn render_table(reports: &[RaceReport]) -> String {
+    let mut table = format!(
+        "{:>16} {:>20} {:>16} {:>24} {:>24} {:>16} {:>16} {:>16}\n",
+        "time", "record", "optimal hold", "max distance", "margin", "winning holds", "slack before", "slack after",
+    );
+    for report in reports.iter() {
+        let (before, after) = match report.slack() {
+            Some((before, after)) => (before.to_string(), after.to_string()),
+            None => ("-".to_string(), "-".to_string()),
+        };
+        table.push_str(&format!(
+            "{:>16} {:>20} {:>16} {:>24} {:>24} {:>16} {:>16} {:>16}\n",
+            report.time,
+            report.record,
+            report.optimal_hold,
+            report.max_distance,
+            report.format_margin(),
+            report.count(),
+            before,
+            after,
+        ));
+    }
+    table
+}
+
+pub fn render_json(reports: &[RaceReport]) -> String {
+    let races: Vec<String> = reports.iter().map(|report| {
+        let (winning, slack) = match (report.winning, report.slack()) {
+            (Some((low, high)), Some((before, after))) => (format!("[{low},{high}]"), format!("[{before},{after}]")),
+            _ => ("null".to_string(), "null".to_string()),
+        };
+        format!(
+            "{{\"time\":{},\"record\":{},\"optimal_hold\":{},\"max_distance\":{},\"margin\":{},\"winning_holds\":{},\"count\":{},\"slack\":{}}}",
+            report.time,
+            report.record,
+            report.optimal_hold,
+            report.max_distance,
+            report.format_margin(),
+            winning,
+            report.count(),
+            slack,
+        )
+    }).collect();
+    format!("[{}]", races.join(","))
+}
@@ -1,42 +1,90 @@
 use std::cmp::Ordering;
-use std::{env, fs::File, path::Path};
+use std::{env, fs, fs::File, path::Path};
 use std::io::{self, BufRead};
-use lazy_static::lazy_static;
-use std::collections::HashMap;
 use counter::Counter;
 
 
-lazy_static! {
-    // static ref CARD_VALUE: HashMap<char, u32> = HashMap::from([
-    //     ('2', 0),
-    //     ('3', 1),
-    //     ('4', 2),
-    //     ('5', 3),
-    //     ('6', 4),
-    //     ('7', 5),
-    //     ('8', 6),
-    //     ('9', 7),
-    //     ('T', 8),
-    //     ('J', 9),
-    //     ('Q', 10),
-    //     ('K', 11),
-    //     ('A', 12),
-    // ]);
-    static ref CARD_VALUE: HashMap<char, u32> = HashMap::from([
-        ('J', 0),
-        ('2', 1),
-        ('3', 2),
-        ('4', 3),
-        ('5', 4),
-        ('6', 5),
-        ('7', 6),
-        ('8', 7),
-        ('9', 8),
-        ('T', 9),
-        ('Q', 10),
-        ('K', 11),
-        ('A', 12),
-    ]);
+/// Order in which cards are compared to break ties between hands of the same type.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum TieBreak {
+    /// Card by card, from the first one dealt (the official rule).
+    FirstToLast,
+    /// Card by card, from the last one dealt.
+    LastToFirst,
+    /// Strongest card first, then the next strongest, regardless of position.
+    Strongest,
+}
+
+/// Everything that differs between Camel Cards variants.
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct Ruleset {
+    /// Cards from weakest to strongest.
+    card_order: Vec<char>,
+    /// Card standing in for whatever makes the best hand type.
+    wild: Option<char>,
+    tie_break: TieBreak,
+}
+
+impl Ruleset {
+    fn part1() -> Self {
+        Ruleset { card_order: "23456789TJQKA".chars().collect(), wild: None, tie_break: TieBreak::FirstToLast }
+    }
+
+    fn part2() -> Self {
+        Ruleset { card_order: "J23456789TQKA".chars().collect(), wild: Some('J'), tie_break: TieBreak::FirstToLast }
+    }
+
+    fn card_value(&self, card: char) -> u32 {
+        self.card_order.iter().position(|c| *c == card).unwrap_or_else(|| panic!("Unknown card: {card}")) as u32
+    }
+}
+
+/// Parses a ruleset file made of `key = value` lines, `#` starting a comment:
+///
+/// ```text
+/// order = J23456789TQKA
+/// wild = J
+/// tie_break = first_to_last
+/// ```
+///
+/// Keys left out keep their part 1 value, and `wild = none` disables wild cards.
+fn parse_ruleset(content: &str) -> Result<Ruleset, String> {
+    let mut ruleset = Ruleset::part1();
+    for (i, line) in content.lines().enumerate() {
+        let line = line.split('#').next().unwrap().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (key, value) = line.split_once('=').ok_or(format!("Line {}: expected key = value", i + 1))?;
+        let value = value.trim();
+        match key.trim() {
+            "order" => ruleset.card_order = value.chars().collect(),
+            "wild" => ruleset.wild = match value {
+                "none" => None,
+                _ if value.chars().count() == 1 => value.chars().next(),
+                _ => return Err(format!("Line {}: wild card must be a single card or none", i + 1)),
+            },
+            "tie_break" => ruleset.tie_break = match value {
+                "first_to_last" => TieBreak::FirstToLast,
+                "last_to_first" => TieBreak::LastToFirst,
+                "strongest" => TieBreak::Strongest,
+                _ => return Err(format!("Line {}: unknown tie break {value}", i + 1)),
+            },
+            other => return Err(format!("Line {}: unknown key {other}", i + 1)),
+        }
+    }
+    let mut seen = ruleset.card_order.clone();
+    seen.sort();
+    seen.dedup();
+    if seen.len() != ruleset.card_order.len() {
+        return Err("Card order lists a card twice".to_string());
+    }
+    if let Some(wild) = ruleset.wild {
+        if !ruleset.card_order.contains(&wild) {
+            return Err(format!("Wild card {wild} is not in the card order"));
+        }
+    }
+    Ok(ruleset)
 }
 
 #[derive(PartialOrd, Ord, PartialEq, Eq, Clone, Copy, Debug)]
@@ -84,110 +132,89 @@ where P: AsRef<Path>, {
     Ok(io::BufReader::new(file).lines())
 }
 
-// fn parse_hand(cards: &Vec<char>) -> ParsedHand {
-//     let converted_cards: Vec<u32> = cards.iter().map(|c| *CARD_VALUE.get(c).unwrap()).collect();
-//     let count = cards.iter().collect::<Counter<_>>();
-//     let mut nb_pair = 0;
-//     let mut nb_triple = 0;
-//     let mut hand_type = HandType::HighCard;
-
-//     for (_, count) in count.most_common() {
-//         if count == 5 {
-//             hand_type = HandType::FiveOfAKind;
-//             break;
-//         }
-//         if count == 4 {
-//             hand_type = HandType::FourOfAKind;
-//             break;
-//         }
-//         if count == 3 {
-//             nb_triple += 1;
-//         }
-//         if count == 2 {
-//             nb_pair += 1;
-//         }
-//     }
-
-//     if hand_type == HandType::HighCard {
-//         hand_type = match (nb_triple, nb_pair) {
-//             (1, 1) => HandType::FullHouse,
-//             (1, 0) => HandType::ThreeOfAKind,
-//             (0, 2) => HandType::TwoPairs,
-//             (0, 1) => HandType::OnePair,
-//             (0, 0) => HandType::HighCard,
-//             _ => todo!(),
-//         };
-//     }
-//     ParsedHand { cards: converted_cards, hand_type: hand_type }
-// }
-
-
-fn parse_hand_joker(cards: &Vec<char>) -> ParsedHand {
-    let converted_cards: Vec<u32> = cards.iter().map(|c| *CARD_VALUE.get(c).unwrap()).collect();
-    let count = cards.iter().collect::<Counter<_>>();
-    let mut nb_pair = 0;
-    let mut nb_triple = 0;
-    let mut hand_type = HandType::HighCard;
-
-    let mut joker_count = *count.get(&'J').unwrap_or(&0);
-
-    for (card, mut count) in count.most_common() {
-        if card != &'J' {
-            count += joker_count;
-            joker_count = 0;
-        } else {
-            if joker_count == 5 {
-                hand_type = HandType::FiveOfAKind;
-                break;
-            } else {
-                continue;
-            }
-        }
-        if count == 5 {
-            hand_type = HandType::FiveOfAKind;
-            break;
-        }
-        if count == 4 {
-            hand_type = HandType::FourOfAKind;
-            break;
-        }
-        if count == 3 {
-            nb_triple += 1;
-        }
-        if count == 2 {
-            nb_pair += 1;
-        }
+fn parse_hand(cards: &[char], ruleset: &Ruleset) -> ParsedHand {
+    let mut converted_cards: Vec<u32> = cards.iter().map(|c| ruleset.card_value(*c)).collect();
+    match ruleset.tie_break {
+        TieBreak::FirstToLast => (),
+        TieBreak::LastToFirst => converted_cards.reverse(),
+        TieBreak::Strongest => converted_cards.sort_by(|a, b| b.cmp(a)),
     }
 
-    if hand_type == HandType::HighCard {
-        hand_type = match (nb_triple, nb_pair) {
-            (1, 1) => HandType::FullHouse,
-            (1, 0) => HandType::ThreeOfAKind,
-            (0, 2) => HandType::TwoPairs,
-            (0, 1) => HandType::OnePair,
-            (0, 0) => HandType::HighCard,
-            _ => todo!(), Perhaps if you have the camel follow the same instructions, you can escape the haunted wasteland!
-        };
+    let count = cards.iter().filter(|c| Some(**c) != ruleset.wild).collect::<Counter<_>>();
+    let wild_count = cards.len() - count.total::<usize>();
+    // Wild cards always do best by joining the largest group
+    let mut groups: Vec<usize> = count.most_common().into_iter().map(|(_, n)| n).collect();
+    match groups.first_mut() {
+        Some(largest) => *largest += wild_count,
+        None => groups.push(wild_count),
     }
-    ParsedHand { cards: converted_cards, hand_type: hand_type }
+
+    let hand_type = match (groups[0], groups.get(1).copied().unwrap_or(0)) {
+        (5, _) => HandType::FiveOfAKind,
+        (4, _) => HandType::FourOfAKind,
+        (3, 2) => HandType::FullHouse,
+        (3, _) => HandType::ThreeOfAKind,
+        (2, 2) => HandType::TwoPairs,
+        (2, _) => HandType::OnePair,
+        _ => HandType::HighCard,
+    };
+    ParsedHand { cards: converted_cards, hand_type }
 }
 
-fn parse_line(line: &str) -> Play {
-    let split: Vec<_> = line.split(" ").collect();
+fn parse_line(line: &str, ruleset: &Ruleset) -> Play {
+    let split: Vec<_> = line.split(' ').collect();
     Play {
-        // hand: parse_hand(&split[0].chars().collect()),
-        hand: parse_hand_joker(&split[0].chars().collect()),
+        hand: parse_hand(&split[0].chars().collect::<Vec<_>>(), ruleset),
         bid: split[1].parse().unwrap(),
     }
 }
 
+fn total_score<I>(lines: I, ruleset: &Ruleset) -> u64
+where I: Iterator<Item = String>, {
+    let mut plays: Vec<_> = lines.map(|l| parse_line(&l, ruleset)).collect();
+    plays.sort_by_key(|play| play.hand.clone());
+    plays.iter().enumerate().map(|(i, play)| ((i as u64) + 1) * play.bid).sum()
+}
+
 
 fn main() {
     let args: Vec<String> = env::args().collect();
     let filename = &args[1];
+    let ruleset = match (args.get(2).map(|s| s.as_str()), args.get(3).map(|s| s.as_str())) {
+        (Some("--part"), Some("1")) => Ruleset::part1(),
+        (Some("--part"), Some("2")) | (None, _) => Ruleset::part2(),
+        (Some("--rules"), Some(path)) => parse_ruleset(&fs::read_to_string(path).unwrap()).unwrap_or_else(|e| {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }),
+        _ => panic!("Usage: day7 <input> [--part 1|2 | --rules <file>]"),
+    };
     let file_iterator = read_lines(filename).unwrap();
-    let mut plays: Vec<_> = file_iterator.map(|l| parse_line(&l.unwrap())).collect();
-    plays.sort_by_key(|play| play.hand.clone());
-    let score: u64 = plays.iter().enumerate().map(|(i, play)| ((i as u64) + 1) * play.bid).sum();
+    let score = total_score(file_iterator.map(|l| l.unwrap()), &ruleset);
     println!("Total score: {score}");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "32T3K 765
+T55J5 684
+KK677 28
+KTJJT 220
+QQQJA 483";
+
+    #[test]
+    fn test_presets() {
+        assert_eq!(total_score(EXAMPLE.lines().map(|l| l.to_string()), &Ruleset::part1()), 6440);
+        assert_eq!(total_score(EXAMPLE.lines().map(|l| l.to_string()), &Ruleset::part2()), 5905);
+    }
+
+    #[test]
+    fn test_parse_ruleset() {
+        let content = "# Part 2 as a file\norder = J23456789TQKA\nwild = J\ntie_break = first_to_last\n";
+        assert_eq!(parse_ruleset(content), Ok(Ruleset::part2()));
+        assert!(parse_ruleset("wild = X").is_err());
+        assert_eq!(parse_hand(&['J', 'J', 'J', 'J', 'J'], &Ruleset::part2()).hand_type, HandType::FiveOfAKind);
+    }
+}